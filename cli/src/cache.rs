@@ -0,0 +1,14 @@
+use clap::{Args, Subcommand};
+
+#[derive(Args, Debug)]
+#[command(about = "manage the on-disk video metadata cache")]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub command: CacheCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    /// delete the cache file
+    Clear,
+}
@@ -1,19 +1,85 @@
 use clap::{Args, value_parser};
 use std::path::PathBuf;
-// use video_encoder::Preset;
-use video_metadata::Resolution;
+use video_encoder::{Preset, ScaleMode, Tonemap, Vfr};
+use video_metadata::{FrameRate, Resolution};
 
 #[derive(Args, Debug)]
 #[command(about = "batch video encoding")]
 pub struct EncodeVideoArgs {
     #[arg(short, long, long_help = "input video or folder")]
     pub inputs: Vec<PathBuf>,
-    // #[arg(short, long, default_value_t = Preset::Medium,long_help = "video encoding preset")]
-    // pub preset: Preset,
-    #[arg(short, long, default_value_t = Resolution::default(),long_help = "limit resolution")]
+    #[arg(
+        short,
+        long,
+        default_value_t = Preset::default(),
+        long_help = "video encoding preset: ultrafast, superfast, veryfast, faster, fast, medium, slow, slower, veryslow, placebo, or an SVT-AV1 level 0-13 (lower is slower and better)"
+    )]
+    pub preset: Preset,
+    #[arg(
+        short,
+        long,
+        default_value_t = Resolution::default(),
+        long_help = "limit resolution by its short side, as WxH or 360p, 480p, 720p, 1080p, 1440p, 4k, 8k"
+    )]
     pub resolution: Resolution,
-    #[arg(short, long, default_value_t = 24,value_parser = value_parser!(u8).range(1..))]
-    pub fps: u8,
+    #[arg(
+        long,
+        default_value_t = ScaleMode::default(),
+        value_name = "MODE",
+        long_help = "fit (keep aspect, only downscale), exact (stretch to the resolution), crop (fill then centre-crop) or pad (fit then letterbox/pillarbox)"
+    )]
+    pub scale_mode: ScaleMode,
+    #[arg(
+        long,
+        default_value = "black",
+        value_name = "COLOR",
+        long_help = "border colour for --scale-mode pad, e.g. black, white or #202020"
+    )]
+    pub pad_color: String,
+    #[arg(
+        short,
+        long,
+        default_value_t = FrameRate::default(),
+        long_help = "limit frame rate, e.g. 24, 30000/1001 or 29.97"
+    )]
+    pub fps: FrameRate,
+    #[arg(
+        long,
+        default_value_t = Vfr::default(),
+        value_name = "MODE",
+        long_help = "variable frame rate sources: keep (untouched), cap (drop frames above --fps) or cfr (convert to constant frame rate)"
+    )]
+    pub vfr: Vfr,
     #[arg(short, long, default_value_t = 1,value_parser = value_parser!(u8).range(1..), help = "folder recursive depth")]
     pub depth: u8,
+    #[arg(
+        long,
+        long_help = "print the ffmpeg command for every video without encoding"
+    )]
+    pub dry_run: bool,
+    #[arg(
+        long,
+        long_help = "output square pixels (SAR 1:1), scaling anamorphic sources to their display aspect"
+    )]
+    pub square_pixels: bool,
+    #[arg(
+        long,
+        default_value_t = Tonemap::default(),
+        value_name = "MODE",
+        long_help = "HDR sources: keep (carry HDR signalling through) or sdr (tone-map to BT.709 on the CPU)"
+    )]
+    pub tonemap: Tonemap,
+    #[arg(
+        long,
+        default_value_t = 120,
+        value_name = "SECS",
+        long_help = "kill ffmpeg if it reports no progress for this many seconds, 0 to disable"
+    )]
+    pub stall_timeout: u64,
+    #[arg(
+        long,
+        value_name = "FACTOR",
+        long_help = "kill ffmpeg if a job runs longer than FACTOR times the video duration"
+    )]
+    pub time_limit: Option<f32>,
 }
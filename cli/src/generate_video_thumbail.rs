@@ -22,4 +22,25 @@ pub struct GenerateVideoThumbnailArgs {
 
     #[arg(short, long, default_value_t = 1,value_parser = value_parser!(u8).range(1..), help = "folder recursive depth")]
     pub depth: u8,
+
+    #[arg(
+        long,
+        long_help = "print the ffmpeg command for every video without generating thumbnails"
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        default_value_t = 120,
+        value_name = "SECS",
+        long_help = "kill ffmpeg if it reports no progress for this many seconds, 0 to disable"
+    )]
+    pub stall_timeout: u64,
+
+    #[arg(
+        long,
+        value_name = "FACTOR",
+        long_help = "kill ffmpeg if a job runs longer than FACTOR times the video duration"
+    )]
+    pub time_limit: Option<f32>,
 }
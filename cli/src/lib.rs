@@ -1,21 +1,63 @@
+mod cache;
 mod encode_video;
 mod generate_video_thumbail;
 
-use clap::{Parser, Subcommand};
+pub use cache::{CacheArgs, CacheCommand};
+use clap::{Parser, Subcommand, ValueEnum};
 pub use encode_video::EncodeVideoArgs;
 pub use generate_video_thumbail::GenerateVideoThumbnailArgs;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(version, about)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        long_help = "ffmpeg executable, defaults to $NOOBTOOL_FFMPEG or ffmpeg in PATH"
+    )]
+    pub ffmpeg: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        long_help = "ffprobe executable, defaults to $NOOBTOOL_FFPROBE or ffprobe in PATH"
+    )]
+    pub ffprobe: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        long_help = "how progress and results are reported"
+    )]
+    pub output: OutputFormat,
+    #[arg(
+        long,
+        global = true,
+        long_help = "always run ffprobe instead of reading or updating the metadata cache"
+    )]
+    pub no_cache: bool,
+}
+
+/// 运行结果的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    #[value(help = "progress bars and logs")]
+    Text,
+    #[value(help = "one JSON event per line on stdout, for scripts")]
+    Json,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     EncodeVideo(EncodeVideoArgs),
     GenerateVideoThumbnail(GenerateVideoThumbnailArgs),
+    Cache(CacheArgs),
 }
 
 #[cfg(test)]
@@ -0,0 +1,31 @@
+/// 单个 ffmpeg 命令行参数单元
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    /// 独立参数，原样作为一个 argv 元素，例如：-y
+    Flag(String),
+    /// 选项及其值，值始终作为一个完整的 argv 元素，例如：-vf scale=1280:-2
+    Pair(String, String),
+}
+
+impl Arg {
+    pub fn flag<S: Into<String>>(flag: S) -> Self {
+        Arg::Flag(flag.into())
+    }
+
+    pub fn pair<K: Into<String>, V: Into<String>>(key: K, value: V) -> Self {
+        Arg::Pair(key.into(), value.into())
+    }
+}
+
+impl IntoIterator for Arg {
+    type Item = String;
+    type IntoIter = std::vec::IntoIter<String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            Arg::Flag(flag) => vec![flag],
+            Arg::Pair(key, value) => vec![key, value],
+        }
+        .into_iter()
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{FfmpegError, error::FfmpegResult};
+use std::{
+    env, fmt, io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+pub const FFMPEG_ENV: &str = "NOOBTOOL_FFMPEG";
+pub const FFPROBE_ENV: &str = "NOOBTOOL_FFPROBE";
+
+/// 最低支持的版本，`-fps_mode` 从 5.1 开始提供
+pub const MIN_VERSION: Version = Version { major: 5, minor: 1 };
+
+static BINARIES: OnceLock<Binaries> = OnceLock::new();
+
+/// ffmpeg 与 ffprobe 可执行文件路径
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binaries {
+    ffmpeg: PathBuf,
+    ffprobe: PathBuf,
+}
+
+impl Default for Binaries {
+    fn default() -> Self {
+        Self::resolve(None, None)
+    }
+}
+
+impl Binaries {
+    /// 确定可执行文件路径，优先级：显式传入（命令行参数或配置） > 环境变量 > PATH 中的 ffmpeg/ffprobe
+    pub fn resolve(ffmpeg: Option<PathBuf>, ffprobe: Option<PathBuf>) -> Self {
+        let pick = |explicit: Option<PathBuf>, var: &str, name: &str| {
+            explicit
+                .or_else(|| {
+                    env::var_os(var)
+                        .filter(|v| !v.is_empty())
+                        .map(PathBuf::from)
+                })
+                .unwrap_or_else(|| PathBuf::from(name))
+        };
+
+        Self {
+            ffmpeg: pick(ffmpeg, FFMPEG_ENV, "ffmpeg"),
+            ffprobe: pick(ffprobe, FFPROBE_ENV, "ffprobe"),
+        }
+    }
+
+    /// 设为全局使用的路径并返回，只有第一次调用生效
+    pub fn init(self) -> &'static Binaries {
+        BINARIES.get_or_init(|| self)
+    }
+
+    /// 全局使用的路径，未调用 `init` 时按默认规则确定
+    pub fn current() -> &'static Binaries {
+        BINARIES.get_or_init(Binaries::default)
+    }
+
+    pub fn ffmpeg(&self) -> &Path {
+        &self.ffmpeg
+    }
+
+    pub fn ffprobe(&self) -> &Path {
+        &self.ffprobe
+    }
+
+    /// 检查 ffmpeg 与 ffprobe 是否可用且版本满足要求
+    pub fn check(&self) -> FfmpegResult<()> {
+        check_version(&self.ffmpeg, MIN_VERSION)?;
+        check_version(&self.ffprobe, MIN_VERSION)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// 运行 `<binary> -version` 并检查版本
+///
+/// 开发版（例如 N-113000-g1234abcd）无法比较版本号，视为满足要求并返回 `None`
+pub fn check_version(binary: &Path, required: Version) -> FfmpegResult<Option<Version>> {
+    let name = binary.to_string_lossy().into_owned();
+
+    let output = Command::new(binary)
+        .arg("-version")
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => FfmpegError::NotFound(name.clone()),
+            _ => FfmpegError::IO(e),
+        })?;
+
+    if !output.status.success() {
+        return Err(FfmpegError::Exit {
+            binary: name,
+            status: output.status.to_string(),
+        });
+    }
+
+    let version = parse_version(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| FfmpegError::UnknownVersion(name.clone()))?;
+
+    match version {
+        Some(found) if found < required => Err(FfmpegError::TooOld {
+            binary: name,
+            found: found.to_string(),
+            required: required.to_string(),
+        }),
+        _ => Ok(version),
+    }
+}
+
+/// 解析 `-version` 输出的第一行，例如：ffmpeg version 6.1.1-3ubuntu5 Copyright (c) ...
+///
+/// 无法识别时返回 `None`，开发版返回 `Some(None)`
+fn parse_version(output: &str) -> Option<Option<Version>> {
+    let mut words = output.lines().next()?.split_whitespace();
+    words.find(|w| *w == "version")?;
+    let raw = words.next()?;
+
+    let numbers: Vec<u32> = raw
+        .trim_start_matches('n')
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map_while(|n| n.parse().ok())
+        .collect();
+
+    match numbers.as_slice() {
+        [major, minor, ..] => Some(Some(Version {
+            major: *major,
+            minor: *minor,
+        })),
+        [major] if !raw.starts_with('N') && !raw.contains("git") => Some(Some(Version {
+            major: *major,
+            minor: 0,
+        })),
+        _ => Some(None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_version_output() {
+        let version = |major, minor| Some(Some(Version { major, minor }));
+
+        assert_eq!(
+            parse_version(
+                "ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023 the FFmpeg developers"
+            ),
+            version(6, 1)
+        );
+        assert_eq!(
+            parse_version("ffprobe version n7.0.2 Copyright (c) 2007-2024 the FFmpeg developers"),
+            version(7, 0)
+        );
+        assert_eq!(
+            parse_version("ffmpeg version 7.1-full_build-www.gyan.dev Copyright (c) 2000-2024"),
+            version(7, 1)
+        );
+        assert_eq!(
+            parse_version("ffmpeg version 4.4.2-0ubuntu0.22.04.1 Copyright (c) 2000-2021"),
+            version(4, 4)
+        );
+        assert_eq!(
+            parse_version("ffmpeg version N-113000-g1234abcd Copyright (c) 2000-2024"),
+            Some(None)
+        );
+        assert_eq!(
+            parse_version("ffmpeg version 2024-03-14-git-599d5a2b2f-full_build-www.gyan.dev"),
+            Some(None)
+        );
+        assert_eq!(parse_version("command not found"), None);
+        assert!(version(4, 4) < version(5, 1));
+    }
+
+    #[test]
+    fn explicit_path_wins() {
+        let binaries = Binaries::resolve(
+            Some(PathBuf::from("/opt/ffmpeg/bin/ffmpeg")),
+            Some(PathBuf::from("/opt/ffmpeg/bin/ffprobe")),
+        );
+        assert_eq!(binaries.ffmpeg(), Path::new("/opt/ffmpeg/bin/ffmpeg"));
+        assert_eq!(binaries.ffprobe(), Path::new("/opt/ffmpeg/bin/ffprobe"));
+    }
+
+    #[test]
+    fn missing_binary() {
+        let result = check_version(Path::new("noobtool-no-such-ffmpeg"), MIN_VERSION);
+        assert!(
+            matches!(result, Err(FfmpegError::NotFound(_))),
+            "{:?}",
+            result
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{Binaries, FfmpegError, error::FfmpegResult};
+use std::{collections::HashSet, io, path::Path, process::Command, sync::OnceLock};
+
+static CAPABILITIES: OnceLock<Capabilities> = OnceLock::new();
+
+/// ffmpeg 支持的编码器、滤镜与封装格式
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Capabilities {
+    encoders: HashSet<String>,
+    filters: HashSet<String>,
+    muxers: HashSet<String>,
+}
+
+impl Capabilities {
+    /// 直接指定支持的能力，用于测试或已知的运行环境
+    pub fn new<E, F, M>(encoders: E, filters: F, muxers: M) -> Self
+    where
+        E: IntoIterator<Item: Into<String>>,
+        F: IntoIterator<Item: Into<String>>,
+        M: IntoIterator<Item: Into<String>>,
+    {
+        Self {
+            encoders: encoders.into_iter().map(Into::into).collect(),
+            filters: filters.into_iter().map(Into::into).collect(),
+            muxers: muxers.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// 运行 `ffmpeg -encoders/-filters/-muxers` 获取支持的能力
+    pub fn probe(binary: &Path) -> FfmpegResult<Self> {
+        Ok(Self {
+            encoders: parse_list(&list(binary, "-encoders")?),
+            filters: parse_list(&list(binary, "-filters")?),
+            muxers: parse_list(&list(binary, "-muxers")?),
+        })
+    }
+
+    /// 全局 ffmpeg 的能力，只探测一次
+    pub fn cached() -> FfmpegResult<&'static Capabilities> {
+        if let Some(capabilities) = CAPABILITIES.get() {
+            return Ok(capabilities);
+        }
+        let capabilities = Self::probe(Binaries::current().ffmpeg())?;
+        Ok(CAPABILITIES.get_or_init(|| capabilities))
+    }
+
+    pub fn has_encoder(&self, name: &str) -> bool {
+        self.encoders.contains(name)
+    }
+
+    pub fn has_filter(&self, name: &str) -> bool {
+        self.filters.contains(name)
+    }
+
+    pub fn has_muxer(&self, name: &str) -> bool {
+        self.muxers.contains(name)
+    }
+
+    pub fn require_encoder(&self, name: &str) -> FfmpegResult<()> {
+        match self.has_encoder(name) {
+            true => Ok(()),
+            false => Err(FfmpegError::MissingEncoder(name.to_string())),
+        }
+    }
+
+    pub fn require_filter(&self, name: &str) -> FfmpegResult<()> {
+        match self.has_filter(name) {
+            true => Ok(()),
+            false => Err(FfmpegError::MissingFilter(name.to_string())),
+        }
+    }
+
+    pub fn require_muxer(&self, name: &str) -> FfmpegResult<()> {
+        match self.has_muxer(name) {
+            true => Ok(()),
+            false => Err(FfmpegError::MissingMuxer(name.to_string())),
+        }
+    }
+
+    /// 按输出文件扩展名检查封装格式，未知扩展名交给 ffmpeg 自行判断
+    pub fn require_muxer_for(&self, output: &Path) -> FfmpegResult<()> {
+        match output.extension() {
+            Some(ext) => self.require_muxer_for_extension(&ext.to_string_lossy()),
+            None => Ok(()),
+        }
+    }
+
+    /// 按扩展名检查封装格式，用于输出文件名确定之前
+    pub fn require_muxer_for_extension(&self, ext: &str) -> FfmpegResult<()> {
+        match muxer_for_extension(&ext.to_ascii_lowercase()) {
+            Some(muxer) => self.require_muxer(muxer),
+            None => Ok(()),
+        }
+    }
+}
+
+fn muxer_for_extension(ext: &str) -> Option<&'static str> {
+    match ext {
+        "mp4" | "m4v" => Some("mp4"),
+        "mkv" => Some("matroska"),
+        "webm" => Some("webm"),
+        "mov" => Some("mov"),
+        "jpg" | "jpeg" | "png" | "webp" => Some("image2"),
+        _ => None,
+    }
+}
+
+fn list(binary: &Path, kind: &str) -> FfmpegResult<String> {
+    let output = Command::new(binary)
+        .args(["-hide_banner", kind])
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => FfmpegError::NotFound(binary.to_string_lossy().into()),
+            _ => FfmpegError::IO(e),
+        })?;
+
+    if !output.status.success() {
+        return Err(FfmpegError::Exit {
+            binary: binary.to_string_lossy().into_owned(),
+            status: output.status.to_string(),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// 解析形如 ` V....D libsvtav1   SVT-AV1...` 的列表，每行第二列是名称
+///
+/// 跳过标题、分隔线以及 ` V..... = Video` 这类图例
+fn parse_list(output: &str) -> HashSet<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let flags = words.next()?;
+            let name = words.next()?;
+            let is_entry = !flags.ends_with(':') && !flags.starts_with('-') && name != "=";
+            is_entry.then_some(name)
+        })
+        // 封装格式可能以逗号分隔多个名称，例如 mov,mp4,m4a
+        .flat_map(|name| name.split(','))
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_encoders() {
+        let encoders = parse_list(
+            "Encoders:
+ V..... = Video
+ A..... = Audio
+ .....D = Supports direct rendering method 1
+ ------
+ V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC (codec h264)
+ V....D libsvtav1            SVT-AV1(Scalable Video Technology for AV1) encoder (codec av1)
+ V.S... mjpeg                MJPEG (Motion JPEG)",
+        );
+        assert!(encoders.contains("libsvtav1"));
+        assert!(encoders.contains("mjpeg"));
+        assert!(!encoders.contains("="));
+        assert_eq!(encoders.len(), 3);
+    }
+
+    #[test]
+    fn parse_filters() {
+        let filters = parse_list(
+            "Filters:
+  T.. = Timeline support
+  .S. = Slice threading
+  A = Audio input/output
+  | = Source or sink filter
+ TSC scale             V->V       Scale the input video size and/or convert the image format.
+ ... tile              V->V       Tile several successive frames together.
+ T.. select            V->N       Select video frames to pass in output.",
+        );
+        assert_eq!(
+            filters,
+            HashSet::from(["scale".into(), "tile".into(), "select".into()])
+        );
+    }
+
+    #[test]
+    fn parse_muxers() {
+        let muxers = parse_list(
+            "File formats:
+ D. = Demuxing supported
+ .E = Muxing supported
+ --
+  E image2          image2 sequence
+  E mp4             MP4 (MPEG-4 Part 14)
+ DE mov,mp4,m4a     QuickTime / MOV",
+        );
+        assert!(muxers.contains("image2"));
+        assert!(muxers.contains("mp4"));
+        assert!(muxers.contains("m4a"));
+    }
+
+    #[test]
+    fn require_missing() {
+        let capabilities = Capabilities::new(["libx264"], ["scale"], ["mp4"]);
+        assert!(capabilities.require_encoder("libx264").is_ok());
+        assert!(matches!(
+            capabilities.require_encoder("libsvtav1"),
+            Err(FfmpegError::MissingEncoder(_))
+        ));
+        assert!(matches!(
+            capabilities.require_filter("tile"),
+            Err(FfmpegError::MissingFilter(_))
+        ));
+        assert!(
+            capabilities
+                .require_muxer_for(Path::new("output.mp4"))
+                .is_ok()
+        );
+        assert!(matches!(
+            capabilities.require_muxer_for(Path::new("output.mkv")),
+            Err(FfmpegError::MissingMuxer(_))
+        ));
+    }
+}
@@ -0,0 +1,31 @@
+use std::io;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FfmpegError {
+    #[error(
+        "{0} not found, install ffmpeg or set it via --ffmpeg/--ffprobe or NOOBTOOL_FFMPEG/NOOBTOOL_FFPROBE"
+    )]
+    NotFound(String),
+    #[error(transparent)]
+    IO(#[from] io::Error),
+    #[error("{binary} exited with status {status}")]
+    Exit { binary: String, status: String },
+    #[error("unrecognized version output from {0}")]
+    UnknownVersion(String),
+    #[error("{binary} version {found} is too old, {required} or newer is required")]
+    TooOld {
+        binary: String,
+        found: String,
+        required: String,
+    },
+    #[error("ffmpeg was built without the {0} encoder")]
+    MissingEncoder(String),
+    #[error("ffmpeg was built without the {0} filter")]
+    MissingFilter(String),
+    #[error("ffmpeg was built without the {0} muxer")]
+    MissingMuxer(String),
+    #[error("output options {0} are not followed by an output file")]
+    TrailingOutputOptions(String),
+}
+
+pub type FfmpegResult<T> = Result<T, FfmpegError>;
@@ -0,0 +1,76 @@
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read},
+    thread::{self, JoinHandle},
+};
+
+/// 最多保留的行数，ffmpeg 出错时真正有用的信息通常在最后几行
+const MAX_LINES: usize = 20;
+/// 单行最多保留的字符数
+const MAX_LINE_LEN: usize = 1024;
+
+/// 在后台线程中读取 ffmpeg 的错误输出（stderr），只保留最后若干行
+///
+/// 必须持续读取，否则管道写满后 ffmpeg 会阻塞
+#[derive(Debug)]
+pub struct ErrorLog {
+    handle: JoinHandle<VecDeque<String>>,
+}
+
+impl ErrorLog {
+    pub fn spawn<R: Read + Send + 'static>(stderr: R) -> Self {
+        let handle = thread::spawn(move || {
+            let mut lines = VecDeque::with_capacity(MAX_LINES);
+            for line in BufReader::new(stderr).split(b'\n').map_while(Result::ok) {
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim_end();
+                if line.is_empty() {
+                    continue;
+                }
+                if lines.len() == MAX_LINES {
+                    lines.pop_front();
+                }
+                lines.push_back(line.chars().take(MAX_LINE_LEN).collect());
+            }
+            lines
+        });
+
+        Self { handle }
+    }
+
+    /// 等待 stderr 关闭并返回收集到的内容
+    pub fn finish(self) -> String {
+        let lines = self.handle.join().unwrap_or_default();
+        match lines.is_empty() {
+            true => "no error output".to_string(),
+            false => Vec::from(lines).join("\n"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn keep_last_lines() {
+        let output = (0..50)
+            .map(|i| format!("line {}", i))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let log = ErrorLog::spawn(Cursor::new(output.into_bytes())).finish();
+        let lines: Vec<&str> = log.lines().collect();
+
+        assert_eq!(lines.len(), MAX_LINES);
+        assert_eq!(lines.first(), Some(&"line 30"));
+        assert_eq!(lines.last(), Some(&"line 49"));
+    }
+
+    #[test]
+    fn empty_output() {
+        let log = ErrorLog::spawn(Cursor::new(Vec::new())).finish();
+        assert_eq!(log, "no error output");
+    }
+}
@@ -0,0 +1,243 @@
+use std::fmt;
+
+/// 单个滤镜，例如：scale=1280:-2、drawtext=text='a b'
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    name: String,
+    args: Vec<FilterArg>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum FilterArg {
+    /// 按位置传递的参数，例如 scale 的 1280
+    Positional(String),
+    /// 具名参数，例如 pad 的 color=black
+    Named(String, String),
+}
+
+impl Filter {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            args: Vec::new(),
+        }
+    }
+
+    /// 添加位置参数
+    pub fn arg<V: ToString>(mut self, value: V) -> Self {
+        self.args.push(FilterArg::Positional(value.to_string()));
+        self
+    }
+
+    /// 添加具名参数
+    pub fn kv<K: Into<String>, V: ToString>(mut self, key: K, value: V) -> Self {
+        self.args
+            .push(FilterArg::Named(key.into(), value.to_string()));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        for (i, arg) in self.args.iter().enumerate() {
+            f.write_str(if i == 0 { "=" } else { ":" })?;
+            match arg {
+                FilterArg::Positional(value) => write!(f, "{}", escape(value))?,
+                FilterArg::Named(key, value) => write!(f, "{}={}", key, escape(value))?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 对参数值做两级转义
+///
+/// 第一级针对滤镜参数（`\`、`'`、`:`），第二级针对滤镜图（`\`、`'`、`[`、`]`、`,`、`;`）
+///
+/// https://ffmpeg.org/ffmpeg-filters.html#Notes-on-filtergraph-escaping
+fn escape(value: &str) -> String {
+    escape_chars(
+        &escape_chars(value, &['\\', '\'', ':']),
+        &['\\', '\'', '[', ']', ',', ';'],
+    )
+}
+
+fn escape_chars(value: &str, special: &[char]) -> String {
+    value.chars().fold(String::new(), |mut escaped, c| {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        escaped
+    })
+}
+
+/// 线性滤镜链，可以带输入输出标签，例如：[0:v]scale=1280:-2,fps=24[v]
+///
+/// 不带标签时可直接用于 -vf
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FilterChain {
+    inputs: Vec<String>,
+    filters: Vec<Filter>,
+    outputs: Vec<String>,
+}
+
+impl FilterChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加输入标签
+    pub fn input<S: Into<String>>(mut self, label: S) -> Self {
+        self.inputs.push(label.into());
+        self
+    }
+
+    /// 追加一个滤镜
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// 添加输出标签
+    pub fn output<S: Into<String>>(mut self, label: S) -> Self {
+        self.outputs.push(label.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    pub fn filters(&self) -> &[Filter] {
+        &self.filters
+    }
+}
+
+impl fmt::Display for FilterChain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for label in &self.inputs {
+            write!(f, "[{}]", label)?;
+        }
+
+        for (i, filter) in self.filters.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", filter)?;
+        }
+
+        for label in &self.outputs {
+            write!(f, "[{}]", label)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 由多条滤镜链组成的滤镜图，用于 -filter_complex
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FilterGraph {
+    chains: Vec<FilterChain>,
+}
+
+impl FilterGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加一条滤镜链
+    pub fn chain(mut self, chain: FilterChain) -> Self {
+        self.chains.push(chain);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chains.iter().all(FilterChain::is_empty)
+    }
+}
+
+impl fmt::Display for FilterGraph {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, chain) in self.chains.iter().enumerate() {
+            if i > 0 {
+                f.write_str(";")?;
+            }
+            write!(f, "{}", chain)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escape_special_chars() {
+        let filter = Filter::new("drawtext")
+            .kv(
+                "text",
+                "this is a 'string': may contain one, or more, special characters",
+            )
+            .kv("fontsize", 24);
+
+        assert_eq!(
+            filter.to_string(),
+            r"drawtext=text=this is a \\\'string\\\'\\: may contain one\, or more\, special characters:fontsize=24"
+        );
+
+        assert_eq!(
+            Filter::new("select").arg("eq(pict_type,I)").to_string(),
+            r"select=eq(pict_type\,I)"
+        );
+    }
+
+    #[test]
+    fn linear_chain() {
+        let chain = FilterChain::new()
+            .filter(Filter::new("scale").arg(1280).arg(-2))
+            .filter(Filter::new("fps").arg(24));
+
+        assert_eq!(chain.to_string(), "scale=1280:-2,fps=24");
+        assert!(FilterChain::new().is_empty());
+    }
+
+    #[test]
+    fn labelled_graph() {
+        let graph = FilterGraph::new()
+            .chain(
+                FilterChain::new()
+                    .input("0:v")
+                    .filter(Filter::new("split").arg(2))
+                    .output("a")
+                    .output("b"),
+            )
+            .chain(
+                FilterChain::new()
+                    .input("a")
+                    .filter(Filter::new("scale").arg(-2).arg(720))
+                    .output("small"),
+            )
+            .chain(
+                FilterChain::new()
+                    .input("b")
+                    .input("small")
+                    .filter(Filter::new("overlay").kv("x", 10).kv("y", 10))
+                    .output("out"),
+            );
+
+        assert_eq!(
+            graph.to_string(),
+            "[0:v]split=2[a][b];[a]scale=-2:720[small];[b][small]overlay=x=10:y=10[out]"
+        );
+    }
+}
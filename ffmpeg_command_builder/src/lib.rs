@@ -1,16 +1,40 @@
-use std::process::Command;
+mod arg;
+mod binary;
+mod capability;
+mod error;
+mod error_log;
+mod filter;
+mod shell;
+
+pub use arg::Arg;
+pub use binary::{Binaries, FFMPEG_ENV, FFPROBE_ENV, MIN_VERSION, Version, check_version};
+pub use capability::Capabilities;
+pub use error::FfmpegError;
+pub use error_log::ErrorLog;
+pub use filter::{Filter, FilterChain, FilterGraph};
+pub use shell::to_shell_string;
+
+use error::FfmpegResult;
+use std::{fmt, process::Command};
 
 #[derive(Debug, Default, Clone)]
 pub struct FfmpegCommandBuilder {
-    global_options: Vec<String>,
+    global_options: Vec<Arg>,
     inputs: Vec<Input>,
-    output_options: Vec<String>,
-    output: Option<String>,
+    outputs: Vec<Output>,
+    /// 尚未绑定输出路径的输出选项，调用 `output` 时归属到该输出
+    pending_output_options: Vec<Arg>,
 }
 
 #[derive(Debug, Clone)]
 struct Input {
-    options: Vec<String>,
+    options: Vec<Arg>,
+    path: String,
+}
+
+#[derive(Debug, Clone)]
+struct Output {
+    options: Vec<Arg>,
     path: String,
 }
 
@@ -19,25 +43,33 @@ impl FfmpegCommandBuilder {
         FfmpegCommandBuilder {
             global_options: Vec::new(),
             inputs: Vec::new(),
-            output_options: Vec::new(),
-            output: None,
+            outputs: Vec::new(),
+            pending_output_options: Vec::new(),
         }
     }
 
-    fn split_long_args<S: AsRef<str>>(args: S) -> Vec<String> {
-        args.as_ref()
-            .split_whitespace()
-            .map(|s| s.to_string())
-            .collect()
+    /// 按空白拆分字符串，仅适用于不含空格的参数值
+    fn split_long_args<S: AsRef<str>>(args: S) -> Vec<Arg> {
+        args.as_ref().split_whitespace().map(Arg::flag).collect()
     }
 
     /// 添加全局选项（例如：-y）
     pub fn global_opt<S: AsRef<str>>(mut self, opt: S) -> Self {
-        let parts: Vec<String> = Self::split_long_args(opt);
-        self.global_options.extend(parts);
+        self.global_options.extend(Self::split_long_args(opt));
         self
     }
 
+    /// 添加一个全局参数，不做任何拆分
+    pub fn global_arg(mut self, arg: Arg) -> Self {
+        self.global_options.push(arg);
+        self
+    }
+
+    /// 添加全局选项及其值（例如：-progress pipe:2）
+    pub fn global_pair<K: Into<String>, V: Into<String>>(self, key: K, value: V) -> Self {
+        self.global_arg(Arg::pair(key, value))
+    }
+
     /// 添加一个输入文件
     pub fn input<S: Into<String>>(mut self, path: S) -> Self {
         self.inputs.push(Input {
@@ -48,49 +80,93 @@ impl FfmpegCommandBuilder {
     }
 
     /// 为最后一个添加的输入文件添加选项（例如：-ss）
-    pub fn input_opt<S: AsRef<str>>(mut self, opt: S) -> Self {
-        let parts: Vec<String> = Self::split_long_args(opt);
+    pub fn input_opt<S: AsRef<str>>(self, opt: S) -> Self {
+        Self::split_long_args(opt)
+            .into_iter()
+            .fold(self, |builder, arg| builder.input_arg(arg))
+    }
+
+    /// 为最后一个添加的输入文件添加一个参数，不做任何拆分
+    pub fn input_arg(mut self, arg: Arg) -> Self {
         if let Some(input) = self.inputs.last_mut() {
-            input.options.extend(parts);
+            input.options.push(arg);
         }
         self
     }
 
-    /// 添加输出选项（例如：-c:v, -b:v）
+    /// 为最后一个添加的输入文件添加选项及其值（例如：-ss 00:00:10）
+    pub fn input_pair<K: Into<String>, V: Into<String>>(self, key: K, value: V) -> Self {
+        self.input_arg(Arg::pair(key, value))
+    }
+
+    /// 为下一个输出文件添加选项（例如：-c:v, -b:v）
     pub fn output_opt<S: AsRef<str>>(mut self, opt: S) -> Self {
-        let parts: Vec<String> = Self::split_long_args(opt);
-        self.output_options.extend(parts);
+        self.pending_output_options
+            .extend(Self::split_long_args(opt));
         self
     }
 
-    /// 设置输出文件路径
+    /// 为下一个输出文件添加一个参数，不做任何拆分
+    pub fn output_arg(mut self, arg: Arg) -> Self {
+        self.pending_output_options.push(arg);
+        self
+    }
+
+    /// 为下一个输出文件添加选项及其值（例如：-c:v libsvtav1）
+    pub fn output_pair<K: Into<String>, V: Into<String>>(self, key: K, value: V) -> Self {
+        self.output_arg(Arg::pair(key, value))
+    }
+
+    /// 添加视频滤镜（-vf），滤镜字符串整体作为一个参数
+    pub fn video_filter<F: fmt::Display>(self, filter: F) -> Self {
+        self.output_pair("-vf", filter.to_string())
+    }
+
+    /// 添加复杂滤镜图（-filter_complex），通过 `-map [label]` 选取其输出
+    pub fn filter_complex<F: fmt::Display>(self, graph: F) -> Self {
+        self.global_pair("-filter_complex", graph.to_string())
+    }
+
+    /// 添加一个输出文件，此前添加的输出选项都归属于该输出
+    ///
+    /// 多次调用即可在一次解码中产生多个输出文件
     pub fn output<S: Into<String>>(mut self, path: S) -> Self {
-        self.output = Some(path.into());
+        self.outputs.push(Output {
+            options: std::mem::take(&mut self.pending_output_options),
+            path: path.into(),
+        });
         self
     }
 
     /// 最终命令构建
-    pub fn build(self) -> Command {
-        let mut command = Command::new("ffmpeg");
+    ///
+    /// 最后一个输出之后还有输出选项时报错，ffmpeg 会忽略这些选项
+    pub fn build(self) -> FfmpegResult<Command> {
+        if !self.pending_output_options.is_empty() {
+            let options = self.pending_output_options.into_iter().flatten();
+            return Err(FfmpegError::TrailingOutputOptions(
+                options.collect::<Vec<_>>().join(" "),
+            ));
+        }
+
+        let mut command = Command::new(Binaries::current().ffmpeg());
 
         // 1. 全局选项
-        command.args(self.global_options);
+        command.args(self.global_options.into_iter().flatten());
 
         // 2. 输入部分：对于每个输入，先加其选项，再加 -i 和路径
         for input in self.inputs {
-            command.args(input.options);
+            command.args(input.options.into_iter().flatten());
             command.arg("-i");
             command.arg(input.path);
         }
 
-        // 3. 输出选项
-        command.args(self.output_options);
-
-        // 4. 输出文件路径
-        if let Some(output) = self.output {
-            command.arg(output);
+        // 3. 输出部分：对于每个输出，先加其选项，再加路径
+        for output in self.outputs {
+            command.args(output.options.into_iter().flatten());
+            command.arg(output.path);
         }
 
-        command
+        Ok(command)
     }
 }
@@ -0,0 +1,97 @@
+use std::{borrow::Cow, ffi::OsStr, process::Command};
+
+/// 将 `Command` 渲染为可直接复制到终端执行的字符串，需要时为参数加上引号
+///
+/// 在 Windows 上按 PowerShell 的规则加引号，结果不适用于 cmd.exe
+pub fn to_shell_string(command: &Command) -> String {
+    let program = quote(command.get_program());
+    // PowerShell 中带引号的程序路径只是字符串，需要用 & 调用
+    let program = match cfg!(windows) && program.starts_with('\'') {
+        true => Cow::Owned(format!("& {}", program)),
+        false => program,
+    };
+
+    std::iter::once(program)
+        .chain(command.get_args().map(quote))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 不需要引号的字符，PowerShell 中 `,` 与 `@` 有特殊含义
+#[cfg(not(windows))]
+const SAFE_CHARS: &str = "-_./:=,+@%";
+#[cfg(windows)]
+const SAFE_CHARS: &str = "-_./:=+\\";
+
+fn is_safe(arg: &str) -> bool {
+    !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || SAFE_CHARS.contains(c))
+}
+
+/// POSIX shell：用单引号包裹，内部的单引号写作 '\''
+#[cfg(not(windows))]
+fn quote(arg: &OsStr) -> Cow<'_, str> {
+    let arg = arg.to_string_lossy();
+    match is_safe(&arg) {
+        true => arg,
+        false => Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''"))),
+    }
+}
+
+/// PowerShell：单引号内不展开变量，内部的单引号写作 ''
+#[cfg(windows)]
+fn quote(arg: &OsStr) -> Cow<'_, str> {
+    let arg = arg.to_string_lossy();
+    match is_safe(&arg) {
+        true => arg,
+        false => Cow::Owned(format!("'{}'", arg.replace('\'', "''"))),
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quote_posix_args() {
+        let mut command = Command::new("ffmpeg");
+        command.args([
+            "-i",
+            "/videos/my video's.mp4",
+            "-vf",
+            r"select=eq(pict_type\,I),scale=1280:-2",
+            "",
+            "out.mp4",
+        ]);
+
+        assert_eq!(
+            to_shell_string(&command),
+            r"ffmpeg -i '/videos/my video'\''s.mp4' -vf 'select=eq(pict_type\,I),scale=1280:-2' '' out.mp4"
+        );
+    }
+}
+
+#[cfg(all(test, windows))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quote_powershell_args() {
+        let mut command = Command::new(r"C:\Program Files\ffmpeg\ffmpeg.exe");
+        command.args([
+            "-i",
+            r"D:\videos\my video's 100%.mp4",
+            "-vf",
+            "scale=1280:-2,fps=24",
+            "",
+            "out.mp4",
+        ]);
+
+        assert_eq!(
+            to_shell_string(&command),
+            r"& 'C:\Program Files\ffmpeg\ffmpeg.exe' -i 'D:\videos\my video''s 100%.mp4' -vf 'scale=1280:-2,fps=24' '' out.mp4"
+        );
+    }
+}
@@ -1,16 +1,16 @@
-use ffmpeg_command_builder::FfmpegCommandBuilder;
+use ffmpeg_command_builder::{Arg, FfmpegCommandBuilder, FfmpegError};
 use std::ffi::OsString;
 use utils::get_command_args;
 
 #[test]
-fn build_command() {
+fn build_command() -> Result<(), FfmpegError> {
     let ffmpeg_command = FfmpegCommandBuilder::new()
         .global_opt("-hide_banner -v error -progress pipe:2")
         .input("input.mp4")
         .input_opt("-ss 00:00:10")
         .output_opt("-c:v libx265 -c:a copy")
         .output("output.mp4")
-        .build();
+        .build()?;
 
     let args = get_command_args(&ffmpeg_command);
 
@@ -22,4 +22,83 @@ fn build_command() {
         "{:#?}",
         args
     );
+
+    Ok(())
+}
+
+#[test]
+fn typed_args_keep_values_intact() -> Result<(), FfmpegError> {
+    let ffmpeg_command = FfmpegCommandBuilder::new()
+        .global_arg(Arg::flag("-y"))
+        .input("my input.mp4")
+        .input_pair("-ss", "00:00:10")
+        .video_filter("drawtext=text='hello world':fontfile=/usr/share/fonts/My Font.ttf")
+        .output_pair("-metadata", "title=a b c")
+        .output("my output.mp4")
+        .build()?;
+
+    let args: Vec<_> = ffmpeg_command.get_args().collect();
+
+    assert_eq!(
+        args,
+        [
+            "-y",
+            "-ss",
+            "00:00:10",
+            "-i",
+            "my input.mp4",
+            "-vf",
+            "drawtext=text='hello world':fontfile=/usr/share/fonts/My Font.ttf",
+            "-metadata",
+            "title=a b c",
+            "my output.mp4",
+        ],
+        "{:#?}",
+        args
+    );
+
+    Ok(())
+}
+
+#[test]
+fn build_multi_output_command() -> Result<(), FfmpegError> {
+    let ffmpeg_command = FfmpegCommandBuilder::new()
+        .global_opt("-hide_banner -y")
+        .input("input.mp4")
+        .output_pair("-c:v", "libsvtav1")
+        .video_filter("scale=-2:1080")
+        .output("output-1080p.mp4")
+        .output_pair("-c:v", "libsvtav1")
+        .video_filter("scale=-2:720")
+        .output("output-720p.mp4")
+        .output_opt("-frames:v 1")
+        .output("poster.jpg")
+        .build()?;
+
+    let args = get_command_args(&ffmpeg_command);
+
+    assert_eq!(
+        args,
+        OsString::from(
+            "-hide_banner -y -i input.mp4 -c:v libsvtav1 -vf scale=-2:1080 output-1080p.mp4 -c:v libsvtav1 -vf scale=-2:720 output-720p.mp4 -frames:v 1 poster.jpg"
+        ),
+        "{:#?}",
+        args
+    );
+
+    Ok(())
+}
+
+#[test]
+fn reject_trailing_output_options() {
+    let result = FfmpegCommandBuilder::new()
+        .input("input.mp4")
+        .output("output.mp4")
+        .output_pair("-c:v", "libsvtav1")
+        .build();
+
+    assert!(matches!(
+        result,
+        Err(FfmpegError::TrailingOutputOptions(options)) if options == "-c:v libsvtav1"
+    ));
 }
@@ -0,0 +1,128 @@
+use crate::{IndicatifSink, ProgressMonitorResult, sink::BatchLink};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::cell::Cell;
+
+/// 批量任务的总体进度，按媒体时长加权
+///
+/// 总体进度条位于最上方，每个文件的进度条嵌套在其下方
+#[derive(Debug)]
+pub struct BatchProgress {
+    multi: MultiProgress,
+    overall: ProgressBar,
+    total_files: usize,
+    done_files: Cell<usize>,
+    /// 已完成文件的总时长，单位毫秒
+    done_ms: Cell<u64>,
+}
+
+impl BatchProgress {
+    pub fn new(total_duration_secs: f32, total_files: usize) -> ProgressMonitorResult<Self> {
+        let multi = MultiProgress::new();
+        let overall = multi.add(ProgressBar::new(secs_to_ms(total_duration_secs)));
+        overall.set_style(
+            ProgressStyle::default_bar()
+                .template("{msg} [{wide_bar}] {percent}% elapsed:{elapsed} eta:{eta}")?
+                .progress_chars("=> "),
+        );
+
+        let batch = Self {
+            multi,
+            overall,
+            total_files,
+            done_files: Cell::new(0),
+            done_ms: Cell::new(0),
+        };
+        batch.update_message();
+
+        Ok(batch)
+    }
+
+    /// 为下一个文件创建进度接收者，`duration_secs` 为该文件的时长
+    pub fn file_sink(&self, duration_secs: f32) -> ProgressMonitorResult<IndicatifSink> {
+        let pb = self.multi.add(ProgressBar::new(100));
+        IndicatifSink::with_bar(
+            pb,
+            Some(BatchLink {
+                overall: self.overall.clone(),
+                base: self.done_ms.get(),
+                len: secs_to_ms(duration_secs),
+            }),
+        )
+    }
+
+    /// 一个文件处理结束，无论成功与否
+    pub fn file_done(&self, duration_secs: f32) {
+        self.done_files.set(self.done_files.get() + 1);
+        self.done_ms
+            .set(self.done_ms.get() + secs_to_ms(duration_secs));
+        self.overall.set_position(self.done_ms.get());
+        self.update_message();
+    }
+
+    pub fn finish(&self) {
+        self.overall.finish_and_clear();
+    }
+
+    /// 暂时隐藏所有进度条后执行 `f`，用于输出日志
+    pub fn suspend<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        self.multi.suspend(f)
+    }
+
+    pub fn done_files(&self) -> usize {
+        self.done_files.get()
+    }
+
+    fn update_message(&self) {
+        self.overall.set_message(format!(
+            "{}/{} files",
+            self.done_files.get(),
+            self.total_files
+        ));
+    }
+}
+
+fn secs_to_ms(secs: f32) -> u64 {
+    (secs.max(0.0) * 1000.0) as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ProgressSink, ProgressSnapshot, ProgressUpdate};
+    use std::time::Duration;
+
+    #[test]
+    fn weighted_by_duration() -> ProgressMonitorResult<()> {
+        let batch = BatchProgress::new(300.0, 2)?;
+
+        let sink = batch.file_sink(100.0)?;
+        let snapshot = ProgressSnapshot {
+            out_time: Some(Duration::from_secs(50)),
+            ..Default::default()
+        };
+        sink.progress(&ProgressUpdate {
+            percent: Some(50),
+            eta: None,
+            snapshot: &snapshot,
+        });
+        assert_eq!(batch.overall.position(), 50_000);
+
+        batch.file_done(100.0);
+        assert_eq!(batch.overall.position(), 100_000);
+        assert_eq!(batch.done_files(), 1);
+
+        let sink = batch.file_sink(200.0)?;
+        let snapshot = ProgressSnapshot {
+            out_time: Some(Duration::from_secs(150)),
+            ..Default::default()
+        };
+        sink.progress(&ProgressUpdate {
+            percent: Some(75),
+            eta: None,
+            snapshot: &snapshot,
+        });
+        assert_eq!(batch.overall.position(), 250_000);
+
+        Ok(())
+    }
+}
@@ -1,16 +1,25 @@
-use indicatif::{ProgressBar, ProgressStyle, style::TemplateError};
+mod batch;
+mod sink;
+mod snapshot;
+
+pub use batch::BatchProgress;
+pub use sink::{IndicatifSink, LogSink, NoopSink, ProgressSink, ProgressUpdate};
+pub use snapshot::{ProgressSnapshot, ProgressState};
+
+use indicatif::style::TemplateError;
 use std::{
     io::{BufRead, BufReader, Read},
     num::{ParseFloatError, ParseIntError},
-    time::Duration,
+    process::Child,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
 };
 
 #[derive(Debug, thiserror::Error)]
 pub enum ProgressMonitorError {
     #[error(transparent)]
     TemplateError(#[from] TemplateError),
-    #[error("Total duration must be greater than zero")]
-    ZeroDuration,
     #[error("Monitor ended without completion")]
     BadEnd,
     #[error("invalid time string: {0}")]
@@ -19,70 +28,172 @@ pub enum ProgressMonitorError {
     ParseFloat(#[from] ParseFloatError),
     #[error(transparent)]
     ParseInt(#[from] ParseIntError),
+    #[error("ffmpeg stdout is not piped")]
+    NoStdout,
+    #[error("no progress from ffmpeg for {0:?}, killed")]
+    Stalled(Duration),
+    #[error("ffmpeg exceeded the time limit of {0:?}, killed")]
+    TimedOut(Duration),
+}
+
+impl ProgressMonitorError {
+    /// 是否因卡住或超时而终止了 ffmpeg
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Self::Stalled(_) | Self::TimedOut(_))
+    }
 }
 
 pub type ProgressMonitorResult<T> = Result<T, ProgressMonitorError>;
 
-#[derive(Debug)]
 pub struct ProgressMonitor {
-    pb: ProgressBar,
+    sink: Box<dyn ProgressSink>,
     total_duration_secs: f32,
+    /// 视频总帧数，时长不可用时据此计算进度
+    total_frames: Option<u64>,
+    msg: String,
+    /// 超过该时长没有任何进度输出即认为 ffmpeg 卡住
+    stall_timeout: Option<Duration>,
+    /// 单个任务允许运行的最长时间
+    time_limit: Option<Duration>,
 }
 
 impl ProgressMonitor {
-    pub fn new(total_duration_secs: f32, msg: String) -> ProgressMonitorResult<Self> {
-        let pb = ProgressBar::new(100);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner} {msg} {percent}% elapsed:{elapsed} eta:{eta}")?,
-        );
-        pb.set_message(msg);
-        Ok(Self {
-            pb,
+    pub fn new(total_duration_secs: f32, msg: String, sink: Box<dyn ProgressSink>) -> Self {
+        Self {
+            sink,
             total_duration_secs,
-        })
+            total_frames: None,
+            msg,
+            stall_timeout: None,
+            time_limit: None,
+        }
+    }
+
+    /// 时长不可用（不大于 0）时改用帧数计算进度，两者都不可用时只显示转圈
+    pub fn with_total_frames(mut self, frames: u64) -> Self {
+        self.total_frames = Some(frames).filter(|frames| *frames > 0);
+        self
+    }
+
+    pub fn with_stall_timeout(mut self, timeout: Duration) -> Self {
+        self.stall_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
     }
 
-    #[allow(clippy::lines_filter_map_ok)]
+    /// 读取子进程 stdout 上的进度，卡住或超时时结束子进程
+    ///
+    /// 子进程需要以 `-progress pipe:1` 启动并将 stdout 设为 `Stdio::piped()`
+    pub fn watch(&self, child: &mut Child) -> ProgressMonitorResult<(Duration, ProgressSnapshot)> {
+        let stdout = child.stdout.take().ok_or(ProgressMonitorError::NoStdout)?;
+        let result = self.process_progress_info(stdout);
+
+        if result.as_ref().is_err_and(ProgressMonitorError::is_timeout) {
+            // 进程可能恰好已经退出，此时 kill 失败无关紧要
+            let _ = child.kill();
+        }
+
+        result
+    }
+
+    /// 读取 ffmpeg `-progress` 输出直到 `progress=end`，返回耗时与最后一组进度数据
     pub fn process_progress_info(
         &self,
-        stderr: impl Read,
-    ) -> ProgressMonitorResult<(Duration, u64)> {
-        if self.total_duration_secs <= 0.0 {
-            return Err(ProgressMonitorError::ZeroDuration);
-        }
+        stdout: impl Read + Send + 'static,
+    ) -> ProgressMonitorResult<(Duration, ProgressSnapshot)> {
+        let start = Instant::now();
+        self.sink.started(&self.msg);
 
-        let mut total_size = 0u64;
-        let mut last_progress = 0u8;
+        let mut snapshot = ProgressSnapshot::default();
+        let lines = spawn_line_reader(stdout);
+        let deadline = self.time_limit.map(|limit| start + limit);
 
-        for line in BufReader::new(stderr).lines().filter_map(Result::ok) {
-            let Some((key, value)) = line.split_once('=') else {
-                continue;
+        loop {
+            let line = match self.max_wait(deadline) {
+                Some(wait) => lines.recv_timeout(wait),
+                None => lines.recv().map_err(|_| RecvTimeoutError::Disconnected),
             };
-            match key {
-                "total_size" => total_size = value.parse()?,
-                "out_time" => {
-                    // 这里省略了错误处理，进度展示不应该影响 ffmpeg 的核心任务
-                    if let Ok(current_secs) = Self::time_string_to_seconds(value) {
-                        let new_progress = (current_secs / self.total_duration_secs * 100.0)
-                            .clamp(0.0, 100.0) as u8;
-
-                        if new_progress.abs_diff(last_progress) >= 1 {
-                            self.pb.set_position(new_progress.into());
-                            last_progress = new_progress;
+
+            let line = match line {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => {
+                    let err = match (deadline, self.time_limit) {
+                        (Some(deadline), Some(limit)) if Instant::now() >= deadline => {
+                            ProgressMonitorError::TimedOut(limit)
                         }
-                    }
+                        _ => ProgressMonitorError::Stalled(self.stall_timeout.unwrap_or_default()),
+                    };
+                    self.sink.failed(&err.to_string());
+                    return Err(err);
                 }
-                "progress" if value == "end" => {
-                    self.pb.finish_and_clear();
-                    return Ok((self.pb.elapsed(), total_size));
-                }
-                // _ => println!("{}", line),
-                _ => {}
+            };
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            // 无法解析的值记为 None，进度展示不应该影响 ffmpeg 的核心任务
+            if !snapshot.apply(key, value) {
+                continue;
+            }
+
+            if snapshot.is_end() {
+                let elapsed = start.elapsed();
+                self.sink.finished(elapsed, &snapshot);
+                return Ok((elapsed, snapshot));
             }
+
+            self.sink.progress(&ProgressUpdate {
+                percent: self.percent(&snapshot),
+                eta: self.eta(&snapshot),
+                snapshot: &snapshot,
+            });
+            snapshot = snapshot.next_block();
+        }
+
+        let err = ProgressMonitorError::BadEnd;
+        self.sink.failed(&err.to_string());
+        Err(err)
+    }
+
+    /// 等待下一行输出的最长时间，`None` 表示一直等待
+    fn max_wait(&self, deadline: Option<Instant>) -> Option<Duration> {
+        let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        match (self.stall_timeout, remaining) {
+            (Some(stall), Some(remaining)) => Some(stall.min(remaining)),
+            (stall, remaining) => stall.or(remaining),
         }
+    }
 
-        Err(ProgressMonitorError::BadEnd)
+    /// 计算百分比，取值 0~100
+    ///
+    /// 优先使用已输出时长，时长不可用时使用已编码帧数，都不可用时返回 `None`
+    fn percent(&self, snapshot: &ProgressSnapshot) -> Option<u8> {
+        let ratio = if self.total_duration_secs > 0.0 {
+            snapshot.out_time?.as_secs_f32() / self.total_duration_secs
+        } else {
+            snapshot.frame? as f32 / self.total_frames? as f32
+        };
+        Some((ratio * 100.0).clamp(0.0, 100.0) as u8)
+    }
+
+    /// 根据 ffmpeg 报告的速度估算剩余时间
+    ///
+    /// `speed` 是每秒实际耗时处理的媒体时长，剩余媒体时长除以它即为剩余时间；
+    /// 按帧计算进度时则用剩余帧数除以编码帧率
+    fn eta(&self, snapshot: &ProgressSnapshot) -> Option<Duration> {
+        let secs = if self.total_duration_secs > 0.0 {
+            let remaining = self.total_duration_secs - snapshot.out_time?.as_secs_f32();
+            remaining.max(0.0) / snapshot.speed.filter(|speed| *speed > 0.0)?
+        } else {
+            let remaining = self.total_frames?.saturating_sub(snapshot.frame?);
+            remaining as f32 / snapshot.fps.filter(|fps| *fps > 0.0)?
+        };
+        Duration::try_from_secs_f32(secs).ok()
     }
 
     pub fn time_string_to_seconds(time_str: &str) -> ProgressMonitorResult<f32> {
@@ -105,16 +216,40 @@ impl ProgressMonitor {
 
         Ok(hours * 3600.0 + minutes * 60.0 + seconds + microseconds)
     }
+}
 
-    pub fn pb(&self) -> ProgressBar {
-        self.pb.clone()
-    }
+/// 在后台线程中逐行读取，使得主线程可以带超时地等待
+fn spawn_line_reader(reader: impl Read + Send + 'static) -> Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(reader).lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    rx
 }
 
 #[cfg(test)]
 mod test {
 
     use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// 记录收到的进度，用于断言
+    #[derive(Default, Clone)]
+    struct RecordSink {
+        percents: Arc<Mutex<Vec<Option<u8>>>>,
+        etas: Arc<Mutex<Vec<Option<Duration>>>>,
+    }
+
+    impl ProgressSink for RecordSink {
+        fn progress(&self, update: &ProgressUpdate) {
+            self.percents.lock().unwrap().push(update.percent);
+            self.etas.lock().unwrap().push(update.eta);
+        }
+    }
 
     fn mock_ffmpeg_output(lines: &[&str]) -> impl std::io::Read {
         let data = lines.join("\n");
@@ -143,7 +278,7 @@ mod test {
 
     #[test]
     fn test_progress_data_parsing() -> ProgressMonitorResult<()> {
-        let monitor = ProgressMonitor::new(100.0, String::default())?;
+        let monitor = ProgressMonitor::new(100.0, String::default(), Box::new(NoopSink));
         let stderr = mock_ffmpeg_output(&[
             "total_size=2048000",
             "out_time=00:00:10.000",
@@ -152,33 +287,106 @@ mod test {
             "progress=end",
         ]);
 
-        let (_, total_size) = monitor.process_progress_info(stderr)?;
-        assert_eq!(total_size, 2048000);
+        let (_, snapshot) = monitor.process_progress_info(stderr)?;
+        assert_eq!(snapshot.total_size, Some(2048000));
+        assert_eq!(snapshot.out_time, Some(Duration::from_secs(20)));
 
         Ok(())
     }
 
     #[test]
     fn test_progress_calculation() -> ProgressMonitorResult<()> {
-        let monitor = ProgressMonitor::new(200.0, String::default())?;
+        let sink = RecordSink::default();
+        let monitor = ProgressMonitor::new(200.0, String::default(), Box::new(sink.clone()));
 
         // 模拟时间推进：50秒 -> 100秒 -> 150秒
         let stderr = mock_ffmpeg_output(&[
             "out_time=00:00:50.000",
+            "progress=continue",
             "out_time=00:01:40.000",
+            "progress=continue",
             "out_time=00:02:30.000",
+            "progress=continue",
         ]);
 
         let result = monitor.process_progress_info(stderr);
         assert!(result.is_err());
-        assert_eq!(monitor.pb().position(), 75);
+        assert_eq!(
+            *sink.percents.lock().unwrap(),
+            [Some(25), Some(50), Some(75)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn eta_from_speed() -> ProgressMonitorResult<()> {
+        let sink = RecordSink::default();
+        let monitor = ProgressMonitor::new(200.0, String::default(), Box::new(sink.clone()));
+
+        // 剩余 150 秒，速度 2 倍 -> 75 秒；速度未知时没有 ETA
+        let stdout = mock_ffmpeg_output(&[
+            "out_time=00:00:50.000",
+            "speed=2x",
+            "progress=continue",
+            "out_time=00:01:40.000",
+            "speed=N/A",
+            "progress=continue",
+            "progress=end",
+        ]);
+
+        monitor.process_progress_info(stdout)?;
+        assert_eq!(
+            *sink.etas.lock().unwrap(),
+            [Some(Duration::from_secs(75)), None]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn fallback_to_frames() -> ProgressMonitorResult<()> {
+        let sink = RecordSink::default();
+        let monitor = ProgressMonitor::new(0.0, String::default(), Box::new(sink.clone()))
+            .with_total_frames(1000);
+
+        let stdout = mock_ffmpeg_output(&[
+            "frame=250",
+            "fps=50.0",
+            "out_time=N/A",
+            "progress=continue",
+            "progress=end",
+        ]);
+
+        monitor.process_progress_info(stdout)?;
+        assert_eq!(*sink.percents.lock().unwrap(), [Some(25)]);
+        assert_eq!(*sink.etas.lock().unwrap(), [Some(Duration::from_secs(15))]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn spinner_without_duration_or_frames() -> ProgressMonitorResult<()> {
+        let sink = RecordSink::default();
+        let monitor = ProgressMonitor::new(0.0, String::default(), Box::new(sink.clone()));
+
+        let stdout = mock_ffmpeg_output(&[
+            "frame=250",
+            "out_time=00:00:10.000",
+            "progress=continue",
+            "progress=end",
+        ]);
+
+        monitor.process_progress_info(stdout)?;
+        assert_eq!(*sink.percents.lock().unwrap(), [None]);
+        assert_eq!(*sink.etas.lock().unwrap(), [None]);
 
         Ok(())
     }
 
     #[test]
     fn test_missing_end_flag() -> ProgressMonitorResult<()> {
-        let monitor = ProgressMonitor::new(100.0, String::default())?;
+        let monitor = ProgressMonitor::new(100.0, String::default(), Box::new(NoopSink));
         let stderr = mock_ffmpeg_output(&["total_size=1024000"]);
 
         let result = monitor.process_progress_info(stderr);
@@ -186,4 +394,96 @@ mod test {
 
         Ok(())
     }
+
+    /// 模拟缓慢输出进度的 ffmpeg：每次读取前先等待一段时间
+    struct SlowReader {
+        lines: std::vec::IntoIter<&'static str>,
+        delay: Duration,
+    }
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            std::thread::sleep(self.delay);
+            match self.lines.next() {
+                Some(line) => {
+                    let data = format!("{}\n", line);
+                    buf[..data.len()].copy_from_slice(data.as_bytes());
+                    Ok(data.len())
+                }
+                None => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn detect_stall() {
+        let monitor = ProgressMonitor::new(100.0, String::default(), Box::new(NoopSink))
+            .with_stall_timeout(Duration::from_millis(50));
+        let stdout = SlowReader {
+            lines: vec!["out_time_us=1000000", "progress=continue"].into_iter(),
+            delay: Duration::from_millis(500),
+        };
+
+        let result = monitor.process_progress_info(stdout);
+        assert!(
+            matches!(result, Err(ProgressMonitorError::Stalled(_))),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn slow_but_steady_progress() -> ProgressMonitorResult<()> {
+        let monitor = ProgressMonitor::new(100.0, String::default(), Box::new(NoopSink))
+            .with_stall_timeout(Duration::from_millis(500));
+        let stdout = SlowReader {
+            lines: vec!["out_time_us=1000000", "progress=continue", "progress=end"].into_iter(),
+            delay: Duration::from_millis(20),
+        };
+
+        monitor.process_progress_info(stdout)?;
+        Ok(())
+    }
+
+    #[test]
+    fn exceed_time_limit() {
+        let monitor = ProgressMonitor::new(100.0, String::default(), Box::new(NoopSink))
+            .with_stall_timeout(Duration::from_secs(5))
+            .with_time_limit(Duration::from_millis(100));
+        let stdout = SlowReader {
+            lines: vec!["progress=continue"; 100].into_iter(),
+            delay: Duration::from_millis(20),
+        };
+
+        let result = monitor.process_progress_info(stdout);
+        assert!(
+            matches!(result, Err(ProgressMonitorError::TimedOut(_))),
+            "{:?}",
+            result
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn kill_stalled_child() {
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("sh")
+            .args([
+                "-c",
+                "echo out_time_us=1000000; echo progress=continue; sleep 30",
+            ])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let monitor = ProgressMonitor::new(100.0, String::default(), Box::new(NoopSink))
+            .with_stall_timeout(Duration::from_millis(200));
+        let start = Instant::now();
+        let result = monitor.watch(&mut child);
+
+        assert!(result.as_ref().is_err_and(ProgressMonitorError::is_timeout));
+        assert!(!child.wait().unwrap().success());
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
 }
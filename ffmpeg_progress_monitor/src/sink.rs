@@ -0,0 +1,244 @@
+use crate::{ProgressMonitorResult, ProgressSnapshot};
+use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
+use std::{
+    fmt::Write,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+/// 一次进度更新
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressUpdate<'a> {
+    /// 百分比，取值 0~100；时长与总帧数都不可用时为 `None`
+    pub percent: Option<u8>,
+    /// 根据 ffmpeg 报告的速度估算的剩余时间
+    pub eta: Option<Duration>,
+    pub snapshot: &'a ProgressSnapshot,
+}
+
+/// 进度事件的接收者，由调用方决定如何展示进度
+///
+/// 要求 `Send`，使持有接收者的 `ProgressMonitor` 可以移到其他线程中运行
+pub trait ProgressSink: Send {
+    /// 任务开始
+    fn started(&self, _message: &str) {}
+
+    /// 每读到一组完整的进度数据时调用
+    fn progress(&self, _update: &ProgressUpdate) {}
+
+    /// 任务正常结束，附带最后一组进度数据
+    fn finished(&self, _elapsed: Duration, _snapshot: &ProgressSnapshot) {}
+
+    /// 任务未能正常结束
+    fn failed(&self, _reason: &str) {}
+}
+
+/// 忽略所有进度事件
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopSink;
+
+impl ProgressSink for NoopSink {}
+
+/// 使用 indicatif 在终端绘制进度条
+#[derive(Debug)]
+pub struct IndicatifSink {
+    pb: ProgressBar,
+    /// 模板中 `{percent}` 与 `{eta}` 的取值，由进度数据计算而非 indicatif 推算
+    state: Arc<Mutex<BarState>>,
+    /// 所属批量任务的总体进度条
+    batch: Option<BatchLink>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct BarState {
+    percent: Option<u8>,
+    eta: Option<Duration>,
+}
+
+/// 单个文件在总体进度条上占据的区间，单位毫秒
+#[derive(Debug)]
+pub(crate) struct BatchLink {
+    pub(crate) overall: ProgressBar,
+    pub(crate) base: u64,
+    pub(crate) len: u64,
+}
+
+impl IndicatifSink {
+    pub fn new() -> ProgressMonitorResult<Self> {
+        Self::with_bar(ProgressBar::new(100), None)
+    }
+
+    pub(crate) fn with_bar(
+        pb: ProgressBar,
+        batch: Option<BatchLink>,
+    ) -> ProgressMonitorResult<Self> {
+        let state = Arc::new(Mutex::new(BarState::default()));
+        let percent = Arc::clone(&state);
+        let eta = Arc::clone(&state);
+
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner} {msg} {percent} elapsed:{elapsed} eta:{eta} {prefix}")?
+                .with_key("percent", move |_: &_, w: &mut dyn Write| {
+                    let _ = match lock(&percent).percent {
+                        Some(percent) => write!(w, "{}%", percent),
+                        None => w.write_str("--%"),
+                    };
+                })
+                .with_key("eta", move |_: &_, w: &mut dyn Write| {
+                    let _ = match lock(&eta).eta {
+                        Some(eta) => write!(w, "{}", HumanDuration(eta)),
+                        None => w.write_str("--"),
+                    };
+                }),
+        );
+        Ok(Self { pb, state, batch })
+    }
+
+    pub fn pb(&self) -> ProgressBar {
+        self.pb.clone()
+    }
+}
+
+impl ProgressSink for IndicatifSink {
+    fn started(&self, message: &str) {
+        self.pb.set_message(message.to_string());
+        self.pb.reset_elapsed();
+    }
+
+    fn progress(&self, update: &ProgressUpdate) {
+        *lock(&self.state) = BarState {
+            percent: update.percent,
+            eta: update.eta,
+        };
+        let snapshot = update.snapshot;
+        match update.percent {
+            Some(percent) => self.pb.set_position(percent.into()),
+            // 进度未知时只让转圈动起来
+            None => self.pb.tick(),
+        }
+        self.pb.set_prefix(rate(snapshot));
+
+        if let Some(link) = &self.batch
+            && let Some(out_time) = snapshot.out_time
+        {
+            let done = (out_time.as_millis() as u64).min(link.len);
+            link.overall.set_position(link.base + done);
+        }
+    }
+
+    fn finished(&self, _elapsed: Duration, _snapshot: &ProgressSnapshot) {
+        self.pb.finish_and_clear();
+    }
+
+    fn failed(&self, _reason: &str) {
+        self.pb.finish_and_clear();
+    }
+}
+
+/// 逐行输出进度日志，适用于非终端环境（例如重定向到文件或在服务器上运行）
+#[derive(Debug)]
+pub struct LogSink {
+    /// 每隔多少个百分点输出一行
+    step: u8,
+    state: Mutex<LogState>,
+}
+
+#[derive(Debug, Default)]
+struct LogState {
+    message: String,
+    last_logged: Option<u8>,
+    /// 进度未知时按时间间隔输出
+    last_logged_at: Option<Instant>,
+}
+
+/// 进度未知时两行日志之间的最短间隔
+const LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+impl Default for LogSink {
+    fn default() -> Self {
+        Self::new(10)
+    }
+}
+
+impl LogSink {
+    pub fn new(step: u8) -> Self {
+        Self {
+            step: step.max(1),
+            state: Default::default(),
+        }
+    }
+}
+
+impl ProgressSink for LogSink {
+    fn started(&self, message: &str) {
+        *lock(&self.state) = LogState {
+            message: message.to_string(),
+            ..LogState::default()
+        };
+        log::info!("{} started", message);
+    }
+
+    fn progress(&self, update: &ProgressUpdate) {
+        let mut state = lock(&self.state);
+        let Some(percent) = update.percent else {
+            let due = state
+                .last_logged_at
+                .is_none_or(|last| last.elapsed() >= LOG_INTERVAL);
+            if due {
+                state.last_logged_at = Some(Instant::now());
+                log::info!("{} {}", state.message, rate(update.snapshot));
+            }
+            return;
+        };
+
+        let due = match state.last_logged {
+            Some(last) => percent >= last.saturating_add(self.step),
+            None => true,
+        };
+        if due {
+            let percent = percent - percent % self.step;
+            state.last_logged = Some(percent);
+            let eta = update.eta.map_or("N/A".to_string(), |eta| {
+                format!("{:.0}s", eta.as_secs_f32())
+            });
+            log::info!(
+                "{} {}% eta:{} {}",
+                state.message,
+                percent,
+                eta,
+                rate(update.snapshot)
+            );
+        }
+    }
+
+    fn finished(&self, elapsed: Duration, _snapshot: &ProgressSnapshot) {
+        log::info!(
+            "{} finished in {:.1}s",
+            lock(&self.state).message,
+            elapsed.as_secs_f32()
+        );
+    }
+
+    fn failed(&self, reason: &str) {
+        log::warn!("{} failed: {}", lock(&self.state).message, reason);
+    }
+}
+
+/// 编码速度与码率，例如：speed:4.34x bitrate:878.8kbits/s
+fn rate(snapshot: &ProgressSnapshot) -> String {
+    let speed = snapshot
+        .speed
+        .map_or("N/A".to_string(), |speed| format!("{:.2}x", speed));
+    let bitrate = snapshot.bitrate.map_or("N/A".to_string(), |bitrate| {
+        format!("{:.1}kbits/s", bitrate)
+    });
+    format!("speed:{} bitrate:{}", speed, bitrate)
+}
+
+/// 进度展示不应该因为锁中毒而中断
+fn lock<T>(state: &Mutex<T>) -> MutexGuard<'_, T> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
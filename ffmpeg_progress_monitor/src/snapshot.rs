@@ -0,0 +1,143 @@
+use std::{str::FromStr, time::Duration};
+
+/// 一组以 `progress=` 行结尾的 ffmpeg `-progress` 数据
+///
+/// ffmpeg 在数据不可用时输出 `N/A`，对应字段为 `None`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProgressSnapshot {
+    pub frame: Option<u64>,
+    pub fps: Option<f32>,
+    /// 单位 kbits/s
+    pub bitrate: Option<f32>,
+    /// 已输出的字节数
+    pub total_size: Option<u64>,
+    /// 已输出的媒体时长
+    pub out_time: Option<Duration>,
+    pub dup_frames: Option<u64>,
+    pub drop_frames: Option<u64>,
+    /// 编码速度相对实时播放的倍数
+    pub speed: Option<f32>,
+    pub state: ProgressState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ProgressState {
+    #[default]
+    Continue,
+    End,
+}
+
+impl ProgressSnapshot {
+    /// 读入一行 `key=value`，读到 `progress=` 时返回 `true` 表示本组数据完整
+    pub(crate) fn apply(&mut self, key: &str, value: &str) -> bool {
+        let value = value.trim();
+        match key.trim() {
+            "frame" => self.frame = parse(value),
+            "fps" => self.fps = parse(value),
+            "bitrate" => self.bitrate = parse(value.trim_end_matches("kbits/s")),
+            "total_size" => self.total_size = parse(value),
+            "out_time_us" => {
+                self.out_time =
+                    parse::<i64>(value).map(|us| Duration::from_micros(us.max(0) as u64))
+            }
+            "out_time" if self.out_time.is_none() => {
+                self.out_time = crate::ProgressMonitor::time_string_to_seconds(value)
+                    .ok()
+                    .map(|secs| Duration::from_secs_f32(secs.max(0.0)))
+            }
+            "dup_frames" => self.dup_frames = parse(value),
+            "drop_frames" => self.drop_frames = parse(value),
+            "speed" => self.speed = parse(value.trim_end_matches('x')),
+            "progress" => {
+                self.state = match value {
+                    "end" => ProgressState::End,
+                    _ => ProgressState::Continue,
+                };
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// 开始读取下一组数据
+    ///
+    /// 沿用上一组的值以应对 ffmpeg 省略部分字段的情况，
+    /// 已输出时长需要重新读取，保证 `out_time_us` 优先于精度较低的 `out_time`
+    pub(crate) fn next_block(&self) -> Self {
+        Self {
+            out_time: None,
+            state: ProgressState::Continue,
+            ..self.clone()
+        }
+    }
+
+    pub fn is_end(&self) -> bool {
+        self.state == ProgressState::End
+    }
+}
+
+fn parse<T: FromStr>(value: &str) -> Option<T> {
+    match value.trim() {
+        "N/A" => None,
+        v => v.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read(data: &str) -> ProgressSnapshot {
+        let mut snapshot = ProgressSnapshot::default();
+        for line in data.lines() {
+            if let Some((key, value)) = line.split_once('=')
+                && snapshot.apply(key, value)
+            {
+                break;
+            }
+        }
+        snapshot
+    }
+
+    #[test]
+    fn parse_complete_block() {
+        let snapshot = read(include_str!("../ffmpeg_progress_info.txt"));
+
+        assert_eq!(
+            snapshot,
+            ProgressSnapshot {
+                frame: Some(338),
+                fps: Some(104.73),
+                bitrate: Some(878.8),
+                total_size: Some(1_539_499),
+                out_time: Some(Duration::from_micros(14_014_014)),
+                dup_frames: Some(0),
+                drop_frames: Some(0),
+                speed: Some(4.34),
+                state: ProgressState::End,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_not_available() {
+        let snapshot = read(
+            "frame=0
+fps=0.00
+bitrate=N/A
+total_size=N/A
+out_time_us=N/A
+out_time=N/A
+speed=N/A
+progress=continue",
+        );
+
+        assert_eq!(snapshot.frame, Some(0));
+        assert_eq!(snapshot.bitrate, None);
+        assert_eq!(snapshot.total_size, None);
+        assert_eq!(snapshot.out_time, None);
+        assert_eq!(snapshot.speed, None);
+        assert!(!snapshot.is_end());
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::{Result, bail};
+use cli::{CacheArgs, CacheCommand};
+use std::path::PathBuf;
+use video_metadata::{Metadata, MetadataCache, MetadataError};
+
+/// 打开默认位置的缓存，`--no-cache` 或无法确定缓存目录时不使用缓存
+pub fn open(no_cache: bool) -> MetadataCache {
+    match MetadataCache::default_path() {
+        Some(path) if !no_cache => MetadataCache::open(path),
+        _ => MetadataCache::disabled(),
+    }
+}
+
+/// 每个视频及其基本信息
+pub type Scanned<'a> = Vec<(&'a PathBuf, Result<Metadata, MetadataError>)>;
+
+/// 批量任务开始前获取所有视频的基本信息，同时返回总时长，总体进度按视频时长加权
+///
+/// 启用 `native` 时优先解析文件头，得到的信息缺少色彩与 HDR，编码前需要重新获取
+pub fn scan<'a>(videos: &'a [PathBuf], cache: &mut MetadataCache) -> (Scanned<'a>, f32) {
+    let scanned: Vec<_> = videos
+        .iter()
+        .map(|video| (video, cache.scan(video)))
+        .collect();
+    let total_duration = scanned
+        .iter()
+        .filter_map(|(_, metadata)| metadata.as_ref().ok())
+        .map(Metadata::duration)
+        .sum();
+    (scanned, total_duration)
+}
+
+pub fn run(args: &CacheArgs) -> Result<bool> {
+    let Some(path) = MetadataCache::default_path() else {
+        bail!("unable to determine the cache directory");
+    };
+
+    match args.command {
+        CacheCommand::Clear => match MetadataCache::clear(&path)? {
+            true => log::info!("removed {}", path.display()),
+            false => log::info!("no cache at {}", path.display()),
+        },
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scan_skips_unreadable_videos() {
+        let videos = [PathBuf::from("/nonexistent/noobtool/a.mp4")];
+        let (scanned, total_duration) = scan(&videos, &mut MetadataCache::disabled());
+
+        assert_eq!(scanned.len(), 1);
+        assert!(scanned[0].1.is_err());
+        assert_eq!(total_duration, 0.0);
+    }
+}
@@ -1,60 +1,156 @@
+use crate::{
+    event::Event,
+    progress::{Job, Reporter, progress_monitor},
+};
 use anyhow::{Result, bail};
 use chrono::Local;
-use cli::EncodeVideoArgs;
-use ffmpeg_progress_monitor::ProgressMonitor;
+use cli::{EncodeVideoArgs, OutputFormat};
+use ffmpeg_command_builder::Capabilities;
 use std::{
     ffi::OsStr,
     path::{Path, PathBuf},
 };
 use utils::{append_suffix_to_path, format_file_size, scan_videos_from_paths};
 use video_encoder::{Config, Encoder};
-use video_metadata::{Metadata, Resolution};
+use video_metadata::{Metadata, MetadataCache};
+
+/// 输出文件的扩展名
+const OUTPUT_EXTENSION: &str = "mp4";
+
+pub fn run(
+    args: &EncodeVideoArgs,
+    output: OutputFormat,
+    cache: &mut MetadataCache,
+) -> Result<bool> {
+    // 缺少编码器、滤镜或封装格式时在扫描之前中止
+    let capabilities = Capabilities::cached()?;
+    Encoder::check_capabilities(capabilities, OUTPUT_EXTENSION)?;
 
-pub fn run(args: &EncodeVideoArgs) -> Result<bool> {
     let input_videos: Vec<PathBuf> = scan_videos_from_paths(&args.inputs, args.depth);
 
     if input_videos.is_empty() {
         bail!("no video found in all your inputs");
     }
 
-    Ok(batch_encode(
-        &input_videos,
-        // &args.preset,
-        &args.resolution,
-        args.fps,
-    ))
+    batch_encode(&input_videos, args, output, capabilities, cache)
 }
 
-fn batch_encode(videos: &[PathBuf], resolution: &Resolution, fps: u8) -> bool {
-    videos.iter().fold(false, |mut has_error, video| {
-        if let Err(e) = process_encode(video, resolution, fps) {
-            log::error!("{e}");
-            has_error = true;
-        }
-        has_error
-    })
+fn batch_encode(
+    videos: &[PathBuf],
+    args: &EncodeVideoArgs,
+    output: OutputFormat,
+    capabilities: &Capabilities,
+    cache: &mut MetadataCache,
+) -> Result<bool> {
+    let (probed, total_duration) = crate::cache::scan(videos, cache);
+
+    let reporter = Reporter::new(output, args.dry_run, total_duration, videos.len())?;
+
+    let failed = probed
+        .into_iter()
+        .enumerate()
+        .fold(0, |failed, (index, (video, scanned))| {
+            reporter.emit(Event::scanned(video));
+            // 与总时长使用同一个来源，总体进度才能走满
+            let duration = scanned.as_ref().map_or(0.0, Metadata::duration);
+            // 文件头中没有色彩与 HDR 信息，编码前换成完整的 ffprobe 结果
+            let metadata = match cfg!(feature = "native") {
+                true => scanned.and_then(|_| cache.retrive(video)),
+                false => scanned,
+            };
+            if let Ok(metadata) = &metadata {
+                reporter.probed(video, metadata);
+            }
+
+            let job = Job {
+                index,
+                total: videos.len(),
+            };
+
+            let result = metadata.map_err(Into::into).and_then(|metadata| {
+                process_encode(video, &metadata, job, args, capabilities, &reporter)
+            });
+            let failed = match result {
+                Ok(_) => failed,
+                Err(e) => {
+                    reporter.error(video, &e);
+                    failed + 1
+                }
+            };
+
+            reporter.file_done(duration);
+            failed
+        });
+
+    reporter.finish();
+    reporter.emit(Event::Summary {
+        command: "encode-video",
+        total: videos.len(),
+        succeeded: videos.len() - failed,
+        failed,
+    });
+
+    Ok(failed > 0)
 }
 
-fn process_encode(input: &Path, resolution: &Resolution, fps: u8) -> Result<()> {
+fn process_encode(
+    input: &Path,
+    metadata: &Metadata,
+    job: Job,
+    args: &EncodeVideoArgs,
+    capabilities: &Capabilities,
+    reporter: &Reporter,
+) -> Result<()> {
     let output = append_suffix_to_path(&input, Local::now().format("%y%m%d%H%M%S").to_string())?
-        .with_extension("mp4");
-    let config = Config::init(input, &output, *resolution, fps);
-    let metadata = Metadata::retrive(input)?;
-    let encoder = Encoder::new(&config, &metadata)?;
-    let stat = encoder.encode(ProgressMonitor::new(
-        metadata.duration(),
-        config.input().to_string_lossy().into_owned(),
-    )?)?;
-
-    let reduction = stat.1 as f64 / metadata.size() as f64;
+        .with_extension(OUTPUT_EXTENSION);
+    let config = Config::init(input, &output, args.resolution, args.preset, args.fps)
+        .with_square_pixels(args.square_pixels)
+        .with_tonemap(args.tonemap)
+        .with_vfr(args.vfr)
+        .with_scale_mode(args.scale_mode)
+        .with_pad_color(&args.pad_color);
+    let encoder = Encoder::new(&config, metadata, capabilities)?;
+
+    if args.dry_run {
+        reporter.dry_run(input, &encoder.build_ffmpeg_command()?);
+        return Ok(());
+    }
+
+    reporter.emit(job.started(input, &output));
+    let stat = encoder.encode(progress_monitor(
+        reporter.sink(input, metadata.duration())?,
+        metadata,
+        job.message(input),
+        args.stall_timeout,
+        args.time_limit,
+    ))?;
+
+    let (elapsed, snapshot) = stat;
+    let file_name = input.file_name().unwrap_or(OsStr::new("unknown file"));
+
+    if let Some(dropped) = snapshot.drop_frames.filter(|&n| n > 0) {
+        reporter.suspend(|| log::warn!("{:?} dropped {} frames", file_name, dropped));
+    }
+
+    let total_size = snapshot.total_size.unwrap_or_default();
+    let reduction = total_size as f64 / metadata.size() as f64;
+    reporter.emit(Event::Completed {
+        input: input.to_string_lossy(),
+        output: output.to_string_lossy(),
+        elapsed_secs: elapsed.as_secs_f32(),
+        size: total_size,
+        ratio: reduction.is_finite().then_some(reduction),
+    });
     if reduction > 1.0 {
-        log::info!(
-            "{:?} output {} ({:.2}% of original)",
-            input.file_name().unwrap_or(OsStr::new("unknown file")),
-            // format_duration(stat.0),
-            format_file_size(stat.1),
-            reduction * 100.0
-        );
+        reporter.suspend(|| {
+            log::info!(
+                "{:?} output {} ({:.2}% of original)",
+                file_name,
+                // format_duration(stat.0),
+                format_file_size(total_size),
+                reduction * 100.0
+            )
+        });
     }
 
     Ok(())
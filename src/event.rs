@@ -0,0 +1,300 @@
+use ffmpeg_progress_monitor::{ProgressSink, ProgressUpdate};
+use serde::Serialize;
+use std::{
+    borrow::Cow,
+    io::{Write, stdout},
+    path::{Path, PathBuf},
+};
+use video_metadata::{Metadata, Sources};
+
+/// `--output json` 时输出到 stdout 的事件，每行一个 JSON 对象
+///
+/// 通过 `event` 字段区分事件类型，例如：{"event":"scanned","input":"a.mp4"}
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// 扫描到一个视频文件
+    Scanned { input: Cow<'a, str> },
+    /// 获取到视频信息
+    Probed {
+        input: Cow<'a, str>,
+        width: u16,
+        height: u16,
+        /// 播放时需要顺时针旋转的角度
+        rotation: u16,
+        /// HDR10 或 HLG，SDR 时为空
+        hdr: Option<String>,
+        fps: f32,
+        /// 是否为可变帧率
+        vfr: bool,
+        duration: f32,
+        size: u64,
+        frames: Option<u64>,
+        /// 时长、帧率与文件大小的来源
+        sources: Sources,
+    },
+    /// 开始处理一个文件
+    JobStarted {
+        /// 从 1 开始的序号，与文本输出中的 `[1/3]` 一致
+        index: usize,
+        total: usize,
+        input: Cow<'a, str>,
+        output: Cow<'a, str>,
+    },
+    /// 一组进度数据
+    Progress {
+        input: Cow<'a, str>,
+        percent: Option<u8>,
+        eta_secs: Option<f32>,
+        frame: Option<u64>,
+        fps: Option<f32>,
+        speed: Option<f32>,
+        bitrate_kbps: Option<f32>,
+        out_time_secs: Option<f32>,
+        total_size: Option<u64>,
+    },
+    /// `--dry-run` 时将要执行的命令
+    DryRun {
+        input: Cow<'a, str>,
+        command: String,
+    },
+    /// 一个文件处理完成
+    Completed {
+        input: Cow<'a, str>,
+        output: Cow<'a, str>,
+        elapsed_secs: f32,
+        /// 输出文件大小，单位字节
+        size: u64,
+        /// 输出文件大小与源文件大小之比
+        ratio: Option<f64>,
+    },
+    /// 出错，与具体文件无关时 `input` 为空
+    Error {
+        input: Option<Cow<'a, str>>,
+        message: String,
+    },
+    /// 所有文件处理完毕
+    Summary {
+        command: &'a str,
+        total: usize,
+        succeeded: usize,
+        failed: usize,
+    },
+}
+
+impl<'a> Event<'a> {
+    pub fn scanned(input: &'a Path) -> Self {
+        Self::Scanned {
+            input: input.to_string_lossy(),
+        }
+    }
+
+    pub fn probed(input: &'a Path, metadata: &Metadata) -> Self {
+        Self::Probed {
+            input: input.to_string_lossy(),
+            width: metadata.width(),
+            height: metadata.height(),
+            rotation: metadata.rotation(),
+            hdr: metadata.hdr().map(|hdr| hdr.to_string()),
+            fps: metadata.fps(),
+            vfr: metadata.is_vfr(),
+            duration: metadata.duration(),
+            size: metadata.size(),
+            frames: metadata.frames(),
+            sources: metadata.sources(),
+        }
+    }
+
+    pub fn error(input: Option<&'a Path>, error: &anyhow::Error) -> Self {
+        Self::Error {
+            input: input.map(Path::to_string_lossy),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// 输出一行事件
+pub fn emit(event: &Event) {
+    let mut out = stdout().lock();
+    // 输出失败（例如管道已关闭）时无处可报告，忽略即可
+    let _ = serde_json::to_writer(&mut out, event)
+        .map_err(std::io::Error::from)
+        .and_then(|_| writeln!(out))
+        .and_then(|_| out.flush());
+}
+
+/// 将进度数据以 `progress` 事件输出
+#[derive(Debug)]
+pub struct JsonSink {
+    input: PathBuf,
+}
+
+impl JsonSink {
+    pub fn new(input: &Path) -> Self {
+        Self {
+            input: input.to_path_buf(),
+        }
+    }
+}
+
+impl ProgressSink for JsonSink {
+    fn progress(&self, update: &ProgressUpdate) {
+        let snapshot = update.snapshot;
+        emit(&Event::Progress {
+            input: self.input.to_string_lossy(),
+            percent: update.percent,
+            eta_secs: update.eta.map(|eta| eta.as_secs_f32()),
+            frame: snapshot.frame,
+            fps: snapshot.fps,
+            speed: snapshot.speed,
+            bitrate_kbps: snapshot.bitrate,
+            out_time_secs: snapshot.out_time.map(|t| t.as_secs_f32()),
+            total_size: snapshot.total_size,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn to_json(event: &Event) -> Value {
+        serde_json::to_value(event).unwrap()
+    }
+
+    #[test]
+    fn serialize_scanned() {
+        assert_eq!(
+            to_json(&Event::scanned(Path::new("a.mp4"))),
+            json!({"event": "scanned", "input": "a.mp4"})
+        );
+    }
+
+    #[test]
+    fn serialize_probed() {
+        let metadata = Metadata::new(1_920, 1_080, 24.0, 60.0, 1_000);
+        assert_eq!(
+            to_json(&Event::probed(Path::new("a.mp4"), &metadata)),
+            json!({
+                "event": "probed",
+                "input": "a.mp4",
+                "width": 1920,
+                "height": 1080,
+                "rotation": 0,
+                "hdr": null,
+                "fps": 24.0,
+                "vfr": false,
+                "duration": 60.0,
+                "size": 1000,
+                "frames": null,
+                "sources": {"duration": "format", "fps": "stream", "size": "format"},
+            })
+        );
+    }
+
+    #[test]
+    fn serialize_job_started() {
+        let event = Event::JobStarted {
+            index: 1,
+            total: 2,
+            input: "a.mp4".into(),
+            output: "b.mp4".into(),
+        };
+        assert_eq!(
+            to_json(&event),
+            json!({"event": "job_started", "index": 1, "total": 2, "input": "a.mp4", "output": "b.mp4"})
+        );
+    }
+
+    #[test]
+    fn serialize_progress() {
+        let event = Event::Progress {
+            input: "a.mp4".into(),
+            percent: Some(50),
+            eta_secs: Some(30.0),
+            frame: Some(720),
+            fps: None,
+            speed: Some(2.0),
+            bitrate_kbps: None,
+            out_time_secs: Some(30.0),
+            total_size: Some(1_024),
+        };
+        assert_eq!(
+            to_json(&event),
+            json!({
+                "event": "progress",
+                "input": "a.mp4",
+                "percent": 50,
+                "eta_secs": 30.0,
+                "frame": 720,
+                "fps": null,
+                "speed": 2.0,
+                "bitrate_kbps": null,
+                "out_time_secs": 30.0,
+                "total_size": 1024,
+            })
+        );
+    }
+
+    #[test]
+    fn serialize_dry_run() {
+        let event = Event::DryRun {
+            input: "a.mp4".into(),
+            command: "ffmpeg -i a.mp4".into(),
+        };
+        assert_eq!(
+            to_json(&event),
+            json!({"event": "dry_run", "input": "a.mp4", "command": "ffmpeg -i a.mp4"})
+        );
+    }
+
+    #[test]
+    fn serialize_completed() {
+        let event = Event::Completed {
+            input: "a.mp4".into(),
+            output: "b.mp4".into(),
+            elapsed_secs: 1.5,
+            size: 512,
+            ratio: Some(0.5),
+        };
+        assert_eq!(
+            to_json(&event),
+            json!({
+                "event": "completed",
+                "input": "a.mp4",
+                "output": "b.mp4",
+                "elapsed_secs": 1.5,
+                "size": 512,
+                "ratio": 0.5,
+            })
+        );
+    }
+
+    #[test]
+    fn serialize_error() {
+        let error = anyhow::anyhow!("no video found");
+        assert_eq!(
+            to_json(&Event::error(None, &error)),
+            json!({"event": "error", "input": null, "message": "no video found"})
+        );
+        assert_eq!(
+            to_json(&Event::error(Some(Path::new("a.mp4")), &error)),
+            json!({"event": "error", "input": "a.mp4", "message": "no video found"})
+        );
+    }
+
+    #[test]
+    fn serialize_summary() {
+        let event = Event::Summary {
+            command: "encode-video",
+            total: 3,
+            succeeded: 2,
+            failed: 1,
+        };
+        assert_eq!(
+            to_json(&event),
+            json!({"event": "summary", "command": "encode-video", "total": 3, "succeeded": 2, "failed": 1})
+        );
+    }
+}
@@ -1,55 +1,135 @@
+use crate::{
+    event::Event,
+    progress::{Job, Reporter, progress_monitor},
+};
 use anyhow::{Result, bail};
 use chrono::Local;
-use cli::GenerateVideoThumbnailArgs;
-use std::path::PathBuf;
+use cli::{GenerateVideoThumbnailArgs, OutputFormat};
+use ffmpeg_command_builder::Capabilities;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 use utils::{append_suffix_to_path, scan_videos_from_paths};
-use video_metadata::Metadata;
-use video_thumbnail::{Generator, Grid};
+use video_metadata::{Metadata, MetadataCache};
+use video_thumbnail::Generator;
+
+/// 输出文件的扩展名
+const OUTPUT_EXTENSION: &str = "jpg";
+
+pub fn run(
+    args: &GenerateVideoThumbnailArgs,
+    output: OutputFormat,
+    cache: &mut MetadataCache,
+) -> Result<bool> {
+    // 缺少编码器、滤镜或封装格式时在扫描之前中止
+    let capabilities = Capabilities::cached()?;
+    Generator::check_capabilities(capabilities, OUTPUT_EXTENSION)?;
 
-pub fn run(args: &GenerateVideoThumbnailArgs) -> Result<bool> {
     let input_videos: Vec<PathBuf> = scan_videos_from_paths(&args.inputs, args.depth);
 
     if input_videos.is_empty() {
         bail!("no video found in all your inputs");
     }
 
-    Ok(generate_thumbnails(input_videos, args.grid, args.base))
+    generate_thumbnails(&input_videos, args, output, cache)
 }
 
-fn generate_thumbnails(videos: Vec<PathBuf>, grid: Grid, base_dimesion: u16) -> bool {
-    let mut errors = vec![];
+fn generate_thumbnails(
+    videos: &[PathBuf],
+    args: &GenerateVideoThumbnailArgs,
+    output: OutputFormat,
+    cache: &mut MetadataCache,
+) -> Result<bool> {
+    let (probed, total_duration) = crate::cache::scan(videos, cache);
+
+    let reporter = Reporter::new(output, args.dry_run, total_duration, videos.len())?;
+
+    let mut errors = 0;
 
-    videos.iter().for_each(|video| {
-        if let Err(e) = generate_thumbnail(video.to_path_buf(), grid, base_dimesion) {
-            errors.push(e);
+    for (index, (video, metadata)) in probed.into_iter().enumerate() {
+        reporter.emit(Event::scanned(video));
+        if let Ok(metadata) = &metadata {
+            reporter.probed(video, metadata);
+        }
+
+        let duration = metadata.as_ref().map_or(0.0, Metadata::duration);
+        let job = Job {
+            index,
+            total: videos.len(),
+        };
+
+        let result = metadata
+            .map_err(Into::into)
+            .and_then(|metadata| generate_thumbnail(video, &metadata, job, args, &reporter));
+        if let Err(e) = result {
+            reporter.error(video, &e);
+            errors += 1;
         }
-    });
 
-    errors.iter().for_each(|e| log::error!("{e}"));
+        reporter.file_done(duration);
+    }
 
-    let success_count = videos.len() - errors.len();
+    reporter.finish();
+    reporter.emit(Event::Summary {
+        command: "generate-video-thumbnail",
+        total: videos.len(),
+        succeeded: videos.len() - errors,
+        failed: errors,
+    });
 
-    log::info!(
-        "generated {} thumbnails,{} failed",
-        success_count,
-        errors.len()
-    );
+    if !args.dry_run {
+        log::info!(
+            "generated {} thumbnails,{} failed",
+            videos.len() - errors,
+            errors
+        );
+    }
 
-    !errors.is_empty()
+    Ok(errors > 0)
 }
 
-pub fn generate_thumbnail(input: PathBuf, grid: Grid, base_dimesion: u16) -> Result<()> {
-    let metadata = Metadata::retrive(&input)?;
+fn generate_thumbnail(
+    input: &Path,
+    metadata: &Metadata,
+    job: Job,
+    args: &GenerateVideoThumbnailArgs,
+    reporter: &Reporter,
+) -> Result<()> {
     let output = append_suffix_to_path(&input, Local::now().format("%y%m%d%H%M%S").to_string())?
-        .with_extension("jpg");
+        .with_extension(OUTPUT_EXTENSION);
     let generator = Generator::new(
-        &input,
+        input,
         &output,
         metadata.duration(),
-        grid,
-        base_dimesion,
+        args.grid,
+        args.base,
         metadata.ratio(),
-    );
+    )?;
+
+    if args.dry_run {
+        reporter.dry_run(input, &generator.build_ffmpeg_command()?);
+        return Ok(());
+    }
+
+    reporter.emit(job.started(input, &output));
+    let (elapsed, _) = generator.generate(progress_monitor(
+        reporter.sink(input, metadata.duration())?,
+        metadata,
+        job.message(input),
+        args.stall_timeout,
+        args.time_limit,
+    ))?;
+
+    let size = fs::metadata(&output)?.len();
+    let ratio = size as f64 / metadata.size() as f64;
+    reporter.emit(Event::Completed {
+        input: input.to_string_lossy(),
+        output: output.to_string_lossy(),
+        elapsed_secs: elapsed.as_secs_f32(),
+        size,
+        ratio: ratio.is_finite().then_some(ratio),
+    });
 
-    Ok(generator.generate()?)
+    Ok(())
 }
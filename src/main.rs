@@ -1,11 +1,16 @@
+mod cache;
 mod encode_video;
+mod event;
 mod generate_video_thumbnail;
+mod progress;
 
 use anyhow::Result;
 use clap::Parser;
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, OutputFormat};
 use env_logger::{Env, WriteStyle};
+use ffmpeg_command_builder::Binaries;
 use std::{io::Write, process};
+use video_metadata::MetadataCache;
 
 fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info"))
@@ -26,9 +31,48 @@ fn main() {
 
 fn run() -> Result<bool> {
     let cli = Cli::parse();
+    let result = dispatch(&cli);
 
+    // 与具体文件无关的错误（例如找不到 ffmpeg）也要让脚本能够读到
+    if cli.output == OutputFormat::Json
+        && let Err(e) = &result
+    {
+        event::emit(&event::Event::error(None, e));
+    }
+
+    result
+}
+
+fn dispatch(cli: &Cli) -> Result<bool> {
     match &cli.command {
-        Commands::EncodeVideo(args) => Ok(encode_video::run(args)?),
-        Commands::GenerateVideoThumbnail(args) => Ok(generate_video_thumbnail::run(args)?),
+        // 管理缓存不需要 ffmpeg
+        Commands::Cache(args) => cache::run(args),
+        Commands::EncodeVideo(args) => {
+            with_ffmpeg(cli, |cache| encode_video::run(args, cli.output, cache))
+        }
+        Commands::GenerateVideoThumbnail(args) => with_ffmpeg(cli, |cache| {
+            generate_video_thumbnail::run(args, cli.output, cache)
+        }),
     }
 }
+
+/// 确认 ffmpeg/ffprobe 可用后运行需要处理视频的子命令，结束后写回视频信息缓存
+fn with_ffmpeg<F>(cli: &Cli, command: F) -> Result<bool>
+where
+    F: FnOnce(&mut MetadataCache) -> Result<bool>,
+{
+    // 在处理任何文件之前确认 ffmpeg/ffprobe 可用
+    Binaries::resolve(cli.ffmpeg.clone(), cli.ffprobe.clone())
+        .init()
+        .check()?;
+
+    let mut cache = cache::open(cli.no_cache);
+    let result = command(&mut cache);
+
+    // 缓存写入失败不影响本次运行的结果
+    if let Err(e) = cache.save() {
+        log::warn!("failed to save metadata cache: {e}");
+    }
+
+    result
+}
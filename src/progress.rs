@@ -0,0 +1,162 @@
+use crate::event::{self, Event, JsonSink};
+use anyhow::Result;
+use cli::OutputFormat;
+use ffmpeg_command_builder::to_shell_string;
+use ffmpeg_progress_monitor::{BatchProgress, LogSink, ProgressMonitor, ProgressSink};
+use std::{
+    io::{IsTerminal, stderr},
+    path::Path,
+    process::Command,
+    time::Duration,
+};
+use video_metadata::Metadata;
+
+/// 批量任务的进度展示
+pub enum Reporter {
+    /// 终端中显示按时长加权的总体进度条，单个文件的进度条嵌套在其下方
+    Terminal(BatchProgress),
+    /// 非终端环境逐行输出进度日志
+    Log,
+    /// 在 stdout 上逐行输出 JSON 事件
+    Json,
+}
+
+impl Reporter {
+    pub fn new(
+        output: OutputFormat,
+        dry_run: bool,
+        total_duration_secs: f32,
+        total_files: usize,
+    ) -> Result<Self> {
+        match output {
+            OutputFormat::Json => Ok(Self::Json),
+            // 只打印命令时没有进度可以展示
+            OutputFormat::Text if dry_run || !stderr().is_terminal() => Ok(Self::Log),
+            OutputFormat::Text => Ok(Self::Terminal(BatchProgress::new(
+                total_duration_secs,
+                total_files,
+            )?)),
+        }
+    }
+
+    /// 为下一个文件创建进度接收者
+    pub fn sink(&self, input: &Path, duration_secs: f32) -> Result<Box<dyn ProgressSink>> {
+        match self {
+            Self::Terminal(batch) => Ok(Box::new(batch.file_sink(duration_secs)?)),
+            Self::Log => Ok(Box::new(LogSink::default())),
+            Self::Json => Ok(Box::new(JsonSink::new(input))),
+        }
+    }
+
+    /// 输出 JSON 事件，其他模式下忽略
+    pub fn emit(&self, event: Event) {
+        if let Self::Json = self {
+            event::emit(&event);
+        }
+    }
+
+    /// 报告获取到的视频信息，时长等信息取自后备来源时记录日志
+    pub fn probed(&self, input: &Path, metadata: &Metadata) {
+        let sources = metadata.sources();
+        if sources.is_fallback() {
+            self.suspend(|| log::info!("{}: {}", input.display(), sources));
+        }
+        self.emit(Event::probed(input, metadata));
+    }
+
+    /// `--dry-run` 时输出将要执行的命令
+    pub fn dry_run(&self, input: &Path, command: &Command) {
+        let command = to_shell_string(command);
+        match self {
+            Self::Json => event::emit(&Event::DryRun {
+                input: input.to_string_lossy(),
+                command,
+            }),
+            _ => println!("{}", command),
+        }
+    }
+
+    /// 报告一个文件处理失败
+    pub fn error(&self, input: &Path, error: &anyhow::Error) {
+        self.suspend(|| log::error!("{error}"));
+        self.emit(Event::error(Some(input), error));
+    }
+
+    /// 一个文件处理结束，无论成功与否
+    pub fn file_done(&self, duration_secs: f32) {
+        if let Self::Terminal(batch) = self {
+            batch.file_done(duration_secs);
+        }
+    }
+
+    pub fn finish(&self) {
+        if let Self::Terminal(batch) = self {
+            batch.finish();
+        }
+    }
+
+    /// 暂时隐藏进度条后执行 `f`，避免日志输出与进度条互相覆盖
+    pub fn suspend<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        match self {
+            Self::Terminal(batch) => batch.suspend(f),
+            Self::Log | Self::Json => f(),
+        }
+    }
+}
+
+/// 批量任务中的一个文件，`index` 从 0 开始
+#[derive(Debug, Clone, Copy)]
+pub struct Job {
+    pub index: usize,
+    pub total: usize,
+}
+
+impl Job {
+    /// 进度展示中的任务描述，例如：[1/3] a.mp4
+    pub fn message(&self, input: &Path) -> String {
+        format!("[{}/{}] {}", self.index + 1, self.total, input.display())
+    }
+
+    pub fn started<'a>(&self, input: &'a Path, output: &'a Path) -> Event<'a> {
+        Event::JobStarted {
+            index: self.index + 1,
+            total: self.total,
+            input: input.to_string_lossy(),
+            output: output.to_string_lossy(),
+        }
+    }
+}
+
+/// 为单个任务创建进度监控
+///
+/// 时长不可用时按 `metadata` 中的总帧数显示进度
+///
+/// - `stall_timeout`：无进度输出的最长秒数，0 表示不检测
+/// - `time_limit`：任务最长运行时间相对视频时长的倍数
+pub fn progress_monitor(
+    sink: Box<dyn ProgressSink>,
+    metadata: &Metadata,
+    msg: String,
+    stall_timeout: u64,
+    time_limit: Option<f32>,
+) -> ProgressMonitor {
+    let duration = metadata.duration();
+    let mut monitor = ProgressMonitor::new(duration, msg, sink);
+
+    if let Some(frames) = metadata.frames() {
+        monitor = monitor.with_total_frames(frames);
+    }
+
+    if stall_timeout > 0 {
+        monitor = monitor.with_stall_timeout(Duration::from_secs(stall_timeout));
+    }
+
+    if let Some(factor) = time_limit
+        && factor > 0.0
+        && duration > 0.0
+    {
+        monitor = monitor.with_time_limit(Duration::from_secs_f32(duration * factor));
+    }
+
+    monitor
+}
@@ -1,12 +1,10 @@
 mod constants;
 mod file;
 mod format;
-mod parse;
 mod path;
 
 pub use file::scan_videos_from_paths;
 pub use format::{format_duration, format_file_size};
-pub use parse::parse_fraction;
 pub use path::{
     append_suffix_to_path, find_videos_within_folder, is_root_path, is_video_path,
     resolve_to_absolute,
@@ -1,6 +1,9 @@
-use video_metadata::Resolution;
-// use crate::preset::Preset;
+use crate::{Preset, ScaleMode, Tonemap, Vfr};
 use std::path::Path;
+use video_metadata::{FrameRate, Resolution};
+
+/// 填充边框的默认颜色
+const DEFAULT_PAD_COLOR: &str = "black";
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Config<'a> {
@@ -11,9 +14,19 @@ pub struct Config<'a> {
     /// 分辨率限制，若输入视频分辨率高于该分辨率则限制到该分辨率，低于该分辨率则使用源视频分辨率
     pub(crate) resolution: Resolution,
     /// 编码器预设
-    // pub(crate) preset: Preset,
-    /// 帧率
-    pub(crate) fps: u8,
+    pub(crate) preset: Preset,
+    /// 帧率限制，源视频帧率高于该帧率时降帧
+    pub(crate) fps: FrameRate,
+    /// 输出方形像素（SAR 1:1），非方形像素的源视频按显示宽高比缩放
+    pub(crate) square_pixels: bool,
+    /// HDR 源视频的处理方式
+    pub(crate) tonemap: Tonemap,
+    /// 可变帧率源视频的处理方式
+    pub(crate) vfr: Vfr,
+    /// 缩放方式
+    pub(crate) scale_mode: ScaleMode,
+    /// `ScaleMode::Pad` 时边框的颜色，例如：black、#202020
+    pub(crate) pad_color: String,
 }
 
 impl<'a> Config<'a> {
@@ -21,18 +34,48 @@ impl<'a> Config<'a> {
         input: &'a Path,
         output: &'a Path,
         resolution: Resolution,
-        // preset: Preset,
-        fps: u8,
+        preset: Preset,
+        fps: FrameRate,
     ) -> Self {
         Config {
             input,
             output,
             resolution,
-            // preset,
+            preset,
             fps,
+            square_pixels: false,
+            tonemap: Tonemap::default(),
+            vfr: Vfr::default(),
+            scale_mode: ScaleMode::default(),
+            pad_color: DEFAULT_PAD_COLOR.to_string(),
         }
     }
 
+    pub fn with_square_pixels(mut self, square_pixels: bool) -> Self {
+        self.square_pixels = square_pixels;
+        self
+    }
+
+    pub fn with_tonemap(mut self, tonemap: Tonemap) -> Self {
+        self.tonemap = tonemap;
+        self
+    }
+
+    pub fn with_vfr(mut self, vfr: Vfr) -> Self {
+        self.vfr = vfr;
+        self
+    }
+
+    pub fn with_scale_mode(mut self, scale_mode: ScaleMode) -> Self {
+        self.scale_mode = scale_mode;
+        self
+    }
+
+    pub fn with_pad_color<S: Into<String>>(mut self, pad_color: S) -> Self {
+        self.pad_color = pad_color.into();
+        self
+    }
+
     pub fn input(&self) -> &Path {
         self.input
     }
@@ -41,13 +84,33 @@ impl<'a> Config<'a> {
         self.resolution
     }
 
-    // pub fn preset(&self) -> Preset {
-    //     self.preset
-    // }
+    pub fn preset(&self) -> Preset {
+        self.preset
+    }
 
-    pub fn fps(&self) -> u8 {
+    pub fn fps(&self) -> FrameRate {
         self.fps
     }
+
+    pub fn square_pixels(&self) -> bool {
+        self.square_pixels
+    }
+
+    pub fn tonemap(&self) -> Tonemap {
+        self.tonemap
+    }
+
+    pub fn vfr(&self) -> Vfr {
+        self.vfr
+    }
+
+    pub fn scale_mode(&self) -> ScaleMode {
+        self.scale_mode
+    }
+
+    pub fn pad_color(&self) -> &str {
+        &self.pad_color
+    }
 }
 
 #[allow(clippy::derivable_impls)]
@@ -57,8 +120,13 @@ impl<'a> Default for Config<'a> {
             input: Path::new("input.mp4"),
             output: Path::new("output.mp4"),
             resolution: Resolution::default(),
-            // preset: Preset::default(),
+            preset: Preset::default(),
             fps: Default::default(),
+            square_pixels: false,
+            tonemap: Tonemap::default(),
+            vfr: Vfr::default(),
+            scale_mode: ScaleMode::default(),
+            pad_color: DEFAULT_PAD_COLOR.to_string(),
         }
     }
 }
@@ -1,75 +1,198 @@
-use crate::{Config, EncoderError, error::EncodeResult};
-use ffmpeg_command_builder::FfmpegCommandBuilder;
-use ffmpeg_progress_monitor::ProgressMonitor;
+use crate::{Config, EncoderError, Preset, ScaleMode, Tonemap, Vfr, error::EncodeResult};
+use ffmpeg_command_builder::{Capabilities, ErrorLog, FfmpegCommandBuilder, Filter, FilterChain};
+use ffmpeg_progress_monitor::{ProgressMonitor, ProgressSnapshot};
 use std::{
-    cmp::{Ordering, min},
+    cmp::{Ordering, max, min},
     path::Path,
     process::{Command, Stdio},
     time::Duration,
 };
-use video_metadata::{Metadata, Orientation, Resolution};
+use video_metadata::{ColorInfo, FrameRate, HdrFormat, Metadata, Orientation, Resolution};
+
+/// 与源视频无关、每次编码都可能用到的滤镜
+const REQUIRED_FILTERS: [&str; 3] = ["scale", "fps", "setsar"];
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Encoder<'a> {
     input: &'a Path,
     output: &'a Path,
-    // preset: Preset,
+    preset: Preset,
     crf: u8,
-    fps: Option<u8>,
+    /// fps 滤镜的目标帧率
+    fps: Option<FrameRate>,
+    /// 保留可变帧率时的最高帧率（`-fpsmax`）
+    fps_max: Option<FrameRate>,
+    /// 转为固定帧率（`-fps_mode cfr`）
+    cfr: bool,
+    /// 输出视频的帧率，用于计算关键帧间隔
+    frame_rate: FrameRate,
     scaled_width: Option<u16>,
     scaled_height: Option<u16>,
+    /// 缩放方式，决定如何缩放到 `scaled_width` 与 `scaled_height`
+    scale_mode: ScaleMode,
+    /// `ScaleMode::Pad` 时边框的颜色
+    pad_color: &'a str,
+    /// 输出方形像素
+    square_pixels: bool,
+    /// 源视频的像素宽高比
+    sar: f32,
+    /// 源视频的色彩信息
+    color: ColorInfo,
+    /// 源视频为 HDR 时的处理方式
+    tonemap: Option<Tonemap>,
 }
 
 impl<'a> Encoder<'a> {
-    pub fn new(config: &'a Config, metadata: &Metadata) -> EncodeResult<Self> {
-        let fps = if metadata.fps() > config.fps().into() {
-            Some(config.fps())
-        } else {
-            None
-        };
+    /// 在扫描视频之前确认 ffmpeg 包含编码器、固定使用的滤镜与输出扩展名对应的封装格式
+    pub fn check_capabilities(
+        capabilities: &Capabilities,
+        output_extension: &str,
+    ) -> EncodeResult<()> {
+        capabilities.require_encoder("libsvtav1")?;
+        for filter in REQUIRED_FILTERS {
+            capabilities.require_filter(filter)?;
+        }
+        capabilities.require_muxer_for_extension(output_extension)?;
+        Ok(())
+    }
 
+    /// 根据配置与源视频信息确定编码参数，并确认 ffmpeg 支持取决于源视频的滤镜
+    ///
+    /// 编码器、固定使用的滤镜与封装格式由 [`Encoder::check_capabilities`] 提前检查
+    pub fn new(
+        config: &'a Config,
+        metadata: &Metadata,
+        capabilities: &Capabilities,
+    ) -> EncodeResult<Self> {
+        let (fps, fps_max, cfr) = Self::compute_frame_rate(config, metadata);
         let (crf, scaled_width, scaled_height) = Self::compute_scaling_params(config, metadata)?;
 
-        Ok(Self {
+        let encoder = Self {
             input: config.input,
             output: config.output,
-            // preset: config.preset(),
+            preset: config.preset(),
             crf,
             fps,
+            fps_max,
+            cfr,
+            frame_rate: fps.or(fps_max).unwrap_or(metadata.frame_rate()),
             scaled_width,
             scaled_height,
-        })
+            scale_mode: config.scale_mode(),
+            pad_color: config.pad_color(),
+            square_pixels: config.square_pixels(),
+            sar: metadata.sar(),
+            color: metadata.color(),
+            tonemap: metadata.hdr().map(|_| config.tonemap()),
+        };
+        encoder.check_filters(capabilities)?;
+
+        Ok(encoder)
+    }
+
+    /// 确认 ffmpeg 包含色调映射、裁剪与填充等按源视频选用的滤镜
+    fn check_filters(&self, capabilities: &Capabilities) -> EncodeResult<()> {
+        if let Some(chain) = self.video_filter() {
+            for filter in chain.filters() {
+                if !REQUIRED_FILTERS.contains(&filter.name()) {
+                    capabilities.require_filter(filter.name())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 计算帧率参数：fps 滤镜的目标帧率、`-fpsmax` 与是否转为固定帧率
+    ///
+    /// # 策略
+    /// - 固定帧率：按精确的分数比较，只在超过帧率限制时降帧，23.976 不会被转为 24
+    /// - 可变帧率：平均帧率低于实际的最高帧率，按基础帧率判断是否超过限制，再按配置保留、限制或转为固定帧率
+    fn compute_frame_rate(
+        config: &Config,
+        metadata: &Metadata,
+    ) -> (Option<FrameRate>, Option<FrameRate>, bool) {
+        let limit = config.fps();
+        if !metadata.is_vfr() {
+            return (
+                (metadata.frame_rate() > limit).then_some(limit),
+                None,
+                false,
+            );
+        }
+
+        let peak = metadata
+            .r_frame_rate()
+            .map_or(metadata.frame_rate(), |r| max(r, metadata.frame_rate()));
+        match config.vfr() {
+            Vfr::Keep => (None, None, false),
+            Vfr::Cap => (None, (peak > limit).then_some(limit), false),
+            Vfr::Cfr => (Some(min(peak, limit)), None, true),
+        }
     }
 
     /// 计算编码缩放参数（CRF和可选的缩放宽高）
     ///
     /// # 策略
-    /// - 分辨率下降时（元数据分辨率≥配置）：根据视频朝向调整宽高，并使用配置的CRF
-    /// - 分辨率上升时（元数据分辨率<配置）：不缩放宽高，使用元数据的CRF
+    /// - 分辨率限制只看短边，横屏与竖屏的源视频都把短边缩小到限制的短边
+    /// - 分辨率下降时（源视频短边≥限制）：长边按显示宽高比换算，根据视频朝向调整宽高，并使用配置的CRF
+    /// - 分辨率上升时（源视频短边<限制）：不缩放宽高，使用元数据的CRF
+    /// - 输出方形像素时：宽高都按显示宽高比计算，非方形像素的源视频即使不缩小也要缩放到显示分辨率
+    /// - 拉伸、裁剪与填充时：输出分辨率限制的准确宽高（按源视频朝向取向），源视频较小时也会放大
     fn compute_scaling_params(
         config: &Config,
         metadata: &Metadata,
     ) -> EncodeResult<(u8, Option<u16>, Option<u16>)> {
-        match metadata.pixels().cmp(&config.resolution().pixels()) {
+        if config.scale_mode() != ScaleMode::Fit {
+            let crf = resolution_to_crf(config.resolution());
+            let resolution = config.resolution();
+            let long_side = even(resolution.get_primary_dimension().into());
+            let short_side = even(resolution.short_side().into());
+            let (width, height) = match metadata.orientation() {
+                Orientation::Landscape => (long_side, short_side),
+                Orientation::Portrait => (short_side, long_side),
+            };
+
+            // 源视频已经是目标宽高时不需要缩放
+            if (metadata.display_width(), metadata.display_height()) == (width, height)
+                && metadata.sar() == 1.0
+            {
+                return Ok((crf, None, None));
+            }
+            return Ok((crf, Some(width), Some(height)));
+        }
+
+        let ratio = metadata.ratio();
+        let limit = config.resolution().short_side();
+        let short_side = min(metadata.display_width(), metadata.display_height());
+
+        match short_side.cmp(&limit) {
             Ordering::Greater | Ordering::Equal => {
                 // 分辨率下降逻辑
                 let crf = resolution_to_crf(config.resolution());
-                let orientation = metadata.resolution()?.get_orientation();
-                let (scaled_width, scaled_height) = match orientation {
-                    Orientation::Landscape => {
-                        let width = config.resolution().get_primary_dimension();
-                        (Some(width), None)
-                    }
-                    Orientation::Portrait => {
-                        let height = config.resolution().get_primary_dimension();
-                        (None, Some(height))
-                    }
-                };
+                // 16:9 的源视频缩放后与限制分辨率的宽高一致
+                let long_side = even(limit as f32 * ratio.max(1.0 / ratio));
+                // 按显示朝向缩放，ffmpeg 会先自动旋转画面
+                let (scaled_width, scaled_height) =
+                    match (metadata.orientation(), config.square_pixels()) {
+                        (Orientation::Landscape, false) => (Some(long_side), None),
+                        (Orientation::Portrait, false) => (None, Some(long_side)),
+                        (Orientation::Landscape, true) => {
+                            (Some(long_side), Some(even(limit.into())))
+                        }
+                        (Orientation::Portrait, true) => {
+                            (Some(even(limit.into())), Some(long_side))
+                        }
+                    };
                 Ok((crf, scaled_width, scaled_height))
             }
             Ordering::Less => {
                 // 分辨率上升逻辑
                 let crf = resolution_to_crf(metadata.resolution()?);
+                if config.square_pixels() && metadata.sar() != 1.0 {
+                    let width = even(metadata.display_width().into());
+                    let height = even(metadata.display_height().into());
+                    return Ok((crf, Some(width), Some(height)));
+                }
                 Ok((crf, None, None))
             }
         }
@@ -78,85 +201,195 @@ impl<'a> Encoder<'a> {
     /// 构建视频编码所需要的 `Command`
     ///
     /// # ffmpeg命令举例
-    /// ffmpeg -hide_banner -v error -progress pipe:2 -i input.mp4 -c:v libsvtav1 -preset 4 -crf 32 -g 240 -svtav1-params tune=0:film-grain=4 -vf scale=1280:-2,fps=24 -c:a copy output.mp4
+    /// ffmpeg -hide_banner -v error -progress pipe:1 -i input.mp4 -c:v libsvtav1 -preset 4 -crf 32 -g 240 -svtav1-params tune=0:film-grain=4 -vf scale=1280:-2,fps=24 -c:a copy output.mp4
     ///
     /// # 参考文档
     /// https://gitlab.com/AOMediaCodec/SVT-AV1/-/blob/master/Docs/Ffmpeg.md
     /// https://gitlab.com/AOMediaCodec/SVT-AV1/-/blob/master/Docs/Parameters.md
-    pub(crate) fn build_ffmpeg_command(&self) -> EncodeResult<Command> {
+    pub fn build_ffmpeg_command(&self) -> EncodeResult<Command> {
         let mut builder = FfmpegCommandBuilder::new()
-            .global_opt("-hide_banner -v error -progress pipe:2")
+            .global_opt("-hide_banner -v error")
+            .global_pair("-progress", "pipe:1")
             .input(self.input.to_string_lossy())
-            .output_opt("-c:v libsvtav1 -preset 4 -crf")
-            .output_opt(self.crf.to_string())
-            .output_opt(format!(
-                "-g {} -svtav1-params tune=0:film-grain=4",
-                self.gop()
-            ));
+            .output_pair("-c:v", "libsvtav1")
+            .output_pair("-preset", self.preset.svt_av1_level().to_string())
+            .output_pair("-crf", self.crf.to_string())
+            .output_pair("-g", self.gop().to_string())
+            .output_pair("-svtav1-params", "tune=0:film-grain=4");
+
+        if let Some(fps_max) = self.fps_max {
+            builder = builder.output_pair("-fpsmax", fps_max.to_string());
+        }
+        if self.cfr {
+            builder = builder.output_pair("-fps_mode", "cfr");
+        }
+
+        for (key, value) in self.color_args() {
+            builder = builder.output_pair(key, value);
+        }
 
         if let Some(vf_str) = self.video_filter() {
-            builder = builder.output_opt(format!("-vf {}", vf_str));
+            builder = builder.video_filter(vf_str);
         }
 
-        let command = builder.output(self.output.to_string_lossy()).build();
+        let command = builder.output(self.output.to_string_lossy()).build()?;
 
         Ok(command)
     }
 
+    /// 关键帧间隔约 10 秒，最多 300 帧
     fn gop(&self) -> u16 {
-        match self.fps {
-            Some(fps) => min((fps as u16) * 10, 300),
-            // 这个值是 cli 的 fps 参数的默认值的 10 倍
-            None => 240,
-        }
+        min((self.frame_rate.as_f32() * 10.0).round() as u16, 300)
     }
 
-    fn video_filter(&self) -> Option<String> {
-        let scale_str = match (self.scaled_width, self.scaled_height) {
-            (Some(w), None) => Some(format!("scale={}:-2", w)),
-            (None, Some(h)) => Some(format!("scale=-2:{}", h)),
-            _ => None,
-        };
+    fn video_filter(&self) -> Option<FilterChain> {
+        let mut chain = FilterChain::new();
+
+        // force_original_aspect_ratio 按存储宽高计算，非方形像素的源视频先拉伸到显示宽高比
+        if matches!(self.scale_mode, ScaleMode::Crop | ScaleMode::Pad)
+            && self.scaled_width.is_some()
+            && self.sar != 1.0
+        {
+            chain = chain
+                .filter(Filter::new("scale").arg("iw*sar").arg("ih"))
+                .filter(Filter::new("setsar").arg(1));
+        }
 
-        let fps_str = self.fps.map(|f| format!("fps={}", f));
+        match (self.scale_mode, self.scaled_width, self.scaled_height) {
+            (ScaleMode::Crop, Some(w), Some(h)) => {
+                chain = chain
+                    .filter(
+                        Filter::new("scale")
+                            .arg(w)
+                            .arg(h)
+                            .kv("force_original_aspect_ratio", "increase"),
+                    )
+                    .filter(Filter::new("crop").arg(w).arg(h));
+            }
+            (ScaleMode::Pad, Some(w), Some(h)) => {
+                // 缩放后的宽高也保持偶数，负数的位置表示居中
+                chain = chain
+                    .filter(
+                        Filter::new("scale")
+                            .arg(w)
+                            .arg(h)
+                            .kv("force_original_aspect_ratio", "decrease")
+                            .kv("force_divisible_by", 2),
+                    )
+                    .filter(
+                        Filter::new("pad")
+                            .arg(w)
+                            .arg(h)
+                            .arg(-1)
+                            .arg(-1)
+                            .kv("color", self.pad_color),
+                    );
+            }
+            (_, Some(w), None) => chain = chain.filter(Filter::new("scale").arg(w).arg(-2)),
+            (_, None, Some(h)) => chain = chain.filter(Filter::new("scale").arg(-2).arg(h)),
+            (_, Some(w), Some(h)) => chain = chain.filter(Filter::new("scale").arg(w).arg(h)),
+            (_, None, None) => {}
+        }
 
-        match (scale_str, fps_str) {
-            (Some(scale), Some(fps)) => Some(format!("{},{}", scale, fps)),
-            (None, Some(fps)) => Some(fps),
-            (Some(scale), None) => Some(scale),
-            _ => None,
+        // 拉伸、裁剪与填充后的画面按方形像素显示
+        let fixed_size = self.scale_mode != ScaleMode::Fit && self.scaled_width.is_some();
+        if self.square_pixels || fixed_size {
+            chain = chain.filter(Filter::new("setsar").arg(1));
         }
+
+        if let Some(fps) = self.fps {
+            chain = chain.filter(Filter::new("fps").arg(fps));
+        }
+
+        // 在缩小与降帧之后再做色调映射，减少需要处理的像素
+        if self.tonemap == Some(Tonemap::Sdr) {
+            for filter in tonemap_filters() {
+                chain = chain.filter(filter);
+            }
+        }
+
+        (!chain.is_empty()).then_some(chain)
     }
 
-    pub fn encode(&self, monitor: ProgressMonitor) -> EncodeResult<(Duration, u64)> {
-        let mut command = self.build_ffmpeg_command()?;
+    /// 色彩相关的输出参数，SDR 源视频不设置
+    ///
+    /// - 保留 HDR：输出 10bit，并携带源视频的色域、传输特性与矩阵系数
+    /// - 映射到 SDR：标记为 BT.709
+    fn color_args(&self) -> Vec<(&'static str, String)> {
+        let hdr = self.color.hdr();
+        match (self.tonemap, hdr) {
+            (Some(Tonemap::Keep), Some(hdr)) => {
+                let mut args = vec![
+                    ("-pix_fmt", "yuv420p10le".to_string()),
+                    (
+                        "-color_primaries",
+                        self.color.primaries.clone().unwrap_or("bt2020".into()),
+                    ),
+                    ("-color_trc", hdr.transfer().to_string()),
+                    (
+                        "-colorspace",
+                        self.color.space.clone().unwrap_or("bt2020nc".into()),
+                    ),
+                ];
+                if let Some(range) = &self.color.range {
+                    args.push(("-color_range", range.clone()));
+                }
+                args
+            }
+            (Some(Tonemap::Sdr), Some(_)) => ["-color_primaries", "-color_trc", "-colorspace"]
+                .into_iter()
+                .map(|key| (key, "bt709".to_string()))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
 
-        let mut child = command.stderr(Stdio::piped()).spawn()?;
+    pub fn encode(&self, monitor: ProgressMonitor) -> EncodeResult<(Duration, ProgressSnapshot)> {
+        let mut command = self.build_ffmpeg_command()?;
 
-        let stderr = child.stderr.take().ok_or(EncoderError::TakeStd)?;
+        // 进度走 stdout，stderr 只剩错误信息
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
 
-        let result = monitor.process_progress_info(stderr)?;
+        let error_log = ErrorLog::spawn(child.stderr.take().ok_or(EncoderError::TakeStd)?);
 
+        let result = monitor.watch(&mut child);
         let status = child.wait()?;
-        if !status.success() {
-            return Err(EncoderError::FfmpegExit(format!("{}", status)));
-        }
 
-        Ok(result)
+        match result {
+            // 卡住或超时时 ffmpeg 已被结束，直接报告原因
+            Err(e) if e.is_timeout() => Err(e.into()),
+            // ffmpeg 异常退出时进度也无法正常结束，优先报告 ffmpeg 的错误信息
+            _ if !status.success() => Err(EncoderError::FfmpegExit {
+                status: status.to_string(),
+                log: error_log.finish(),
+            }),
+            result => Ok(result?),
+        }
     }
 
-    // pub fn preset(&self) -> Preset {
-    //     self.preset
-    // }
+    pub fn preset(&self) -> Preset {
+        self.preset
+    }
 
     pub fn crf(&self) -> u8 {
         self.crf
     }
 
-    pub fn fps(&self) -> Option<u8> {
+    pub fn fps(&self) -> Option<FrameRate> {
         self.fps
     }
 
+    pub fn fps_max(&self) -> Option<FrameRate> {
+        self.fps_max
+    }
+
+    pub fn cfr(&self) -> bool {
+        self.cfr
+    }
+
     pub fn scaled_width(&self) -> Option<u16> {
         self.scaled_width
     }
@@ -164,6 +397,15 @@ impl<'a> Encoder<'a> {
     pub fn scaled_height(&self) -> Option<u16> {
         self.scaled_height
     }
+
+    pub fn square_pixels(&self) -> bool {
+        self.square_pixels
+    }
+
+    /// 源视频为 HDR 时的传输特性
+    pub fn hdr(&self) -> Option<HdrFormat> {
+        self.color.hdr()
+    }
 }
 
 impl<'a> Default for Encoder<'a> {
@@ -171,26 +413,105 @@ impl<'a> Default for Encoder<'a> {
         Self {
             input: Path::new("input.mp4"),
             output: Path::new("output.mp4"),
+            preset: Default::default(),
             crf: Default::default(),
             fps: Default::default(),
+            fps_max: Default::default(),
+            cfr: Default::default(),
+            frame_rate: Default::default(),
             scaled_width: Default::default(),
             scaled_height: Default::default(),
+            scale_mode: Default::default(),
+            pad_color: Default::default(),
+            square_pixels: Default::default(),
+            sar: 1.0,
+            color: Default::default(),
+            tonemap: Default::default(),
         }
     }
 }
 
+/// HDR 到 SDR 的 CPU 色调映射：转为线性光，在 BT.709 色域中用 hable 曲线压缩亮度，再转回 BT.709
+///
+/// https://ffmpeg.org/ffmpeg-filters.html#tonemap-1
+fn tonemap_filters() -> [Filter; 6] {
+    [
+        Filter::new("zscale").kv("t", "linear").kv("npl", 100),
+        Filter::new("format").arg("gbrpf32le"),
+        Filter::new("zscale").kv("p", "bt709"),
+        Filter::new("tonemap").kv("tonemap", "hable").kv("desat", 0),
+        Filter::new("zscale")
+            .kv("t", "bt709")
+            .kv("m", "bt709")
+            .kv("r", "tv"),
+        Filter::new("format").arg("yuv420p"),
+    ]
+}
+
+/// 取最接近的偶数，yuv420p 要求宽高为偶数
+fn even(value: f32) -> u16 {
+    ((value / 2.0).round() * 2.0) as u16
+}
+
+/// 分辨率越高，同样的 CRF 下细节越不明显，可以用更高的 CRF
+///
 /// https://handbrake.fr/docs/en/1.9.0/workflow/adjust-quality.html
 fn resolution_to_crf(resolution: Resolution) -> u8 {
     match resolution.pixels() {
+        p if p >= Resolution::Uhd.pixels() => 28,
         p if p >= Resolution::Hd.pixels() => 25,
-        _ => 22,
+        p if p >= Resolution::Fwvga.pixels() => 22,
+        _ => 20,
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use ffmpeg_command_builder::FfmpegError;
     use utils::get_command_args;
+    use video_metadata::{CodecType, Format, Probe, SideData, Stream};
+
+    fn capabilities() -> Capabilities {
+        Capabilities::new(
+            ["libsvtav1"],
+            [
+                "scale", "fps", "setsar", "crop", "pad", "zscale", "format", "tonemap",
+            ],
+            ["mp4"],
+        )
+    }
+
+    /// 只有一个视频流的源视频
+    fn from_stream(stream: Stream) -> Metadata {
+        let probe = Probe {
+            streams: vec![stream],
+            format: Format {
+                size: Some(1),
+                ..Format::default()
+            },
+        };
+        Metadata::from_probe(probe).unwrap()
+    }
+
+    /// DVD：720x480，SAR 32:27，显示为 16:9
+    fn dvd_stream() -> Stream {
+        Stream {
+            codec_type: CodecType::Video,
+            width: Some(720),
+            height: Some(480),
+            sample_aspect_ratio: Some("32:27".into()),
+            avg_frame_rate: Some("24/1".into()),
+            ..Stream::default()
+        }
+    }
+
+    /// 构建 ffmpeg 命令，返回以空格连接的参数
+    fn command_args(config: &Config, metadata: &Metadata) -> EncodeResult<String> {
+        let encoder = Encoder::new(config, metadata, &capabilities())?;
+        let command = encoder.build_ffmpeg_command()?;
+        Ok(get_command_args(&command).to_string_lossy().to_string())
+    }
 
     #[test]
     fn source_downscale_to_config() -> EncodeResult<()> {
@@ -198,44 +519,36 @@ mod test {
         let metadata = Metadata::new(1_920, 1_080, 30.0, 0.0, 0);
         let config = Config {
             resolution: Resolution::Hd,
-            fps: 24,
+            fps: FrameRate::integer(24).unwrap(),
             ..Config::default()
         };
-        let encoder = Encoder::new(&config, &metadata)?;
-        let command = encoder.build_ffmpeg_command()?;
-        let args = get_command_args(&command).to_string_lossy().to_string();
+        let args = command_args(&config, &metadata)?;
         assert!(args.contains("-crf 25 -g 240"));
         assert!(args.contains("-vf scale=1280:-2,fps=24"));
 
         // 源视频横屏，配置竖屏
         let config = Config {
             resolution: Resolution::Vhd,
-            fps: 24,
+            fps: FrameRate::integer(24).unwrap(),
             ..Config::default()
         };
-        let encoder = Encoder::new(&config, &metadata)?;
-        let command = encoder.build_ffmpeg_command()?;
-        let args = get_command_args(&command).to_string_lossy().to_string();
+        let args = command_args(&config, &metadata)?;
         assert!(args.contains("-crf 25 -g 240"));
         assert!(args.contains("-vf scale=1280:-2,fps=24"));
 
         // 源视频竖屏，配置竖屏
         let metadata = Metadata::new(1_080, 1_920, 30.0, 0.0, 0);
-        let encoder = Encoder::new(&config, &metadata)?;
-        let command = encoder.build_ffmpeg_command()?;
-        let args = get_command_args(&command).to_string_lossy().to_string();
+        let args = command_args(&config, &metadata)?;
         assert!(args.contains("-crf 25 -g 240"));
         assert!(args.contains("-vf scale=-2:1280,fps=24"), "{}", args);
 
         // 源视频竖屏，配置竖屏
         let config = Config {
             resolution: Resolution::Hd,
-            fps: 24,
+            fps: FrameRate::integer(24).unwrap(),
             ..Config::default()
         };
-        let encoder = Encoder::new(&config, &metadata)?;
-        let command = encoder.build_ffmpeg_command()?;
-        let args = get_command_args(&command).to_string_lossy().to_string();
+        let args = command_args(&config, &metadata)?;
         assert!(args.contains("-crf 25 -g 240"));
         assert!(args.contains("-vf scale=-2:1280,fps=24"));
 
@@ -247,40 +560,441 @@ mod test {
         // 横屏
         let metadata = Metadata::new(1_920, 1_080, 24.0, 0.0, 0);
         let config = Config {
-            fps: 24,
+            fps: FrameRate::integer(24).unwrap(),
             resolution: Resolution::Qhd,
             ..Config::default()
         };
-        let encoder = Encoder::new(&config, &metadata)?;
-        let command = encoder.build_ffmpeg_command()?;
-        let args = get_command_args(&command).to_string_lossy().to_string();
+        let args = command_args(&config, &metadata)?;
         assert!(args.contains("-crf 25 -g 240"), "{}", args);
         assert!(!args.contains("-vf"));
 
         // 竖屏
         let config = Config {
             resolution: Resolution::Vqhd,
-            fps: 24,
+            fps: FrameRate::integer(24).unwrap(),
             ..Config::default()
         };
-        let encoder = Encoder::new(&config, &metadata)?;
-        let command = encoder.build_ffmpeg_command()?;
-        let args = get_command_args(&command).to_string_lossy().to_string();
+        let args = command_args(&config, &metadata)?;
         assert!(args.contains("-crf 25 -g 240"));
         assert!(!args.contains("-vf"));
 
         // 没有缩放但有fps限制
         let config = Config {
             resolution: Resolution::Vqhd,
-            fps: 20,
+            fps: FrameRate::integer(20).unwrap(),
             ..Config::default()
         };
-        let encoder = Encoder::new(&config, &metadata)?;
-        let command = encoder.build_ffmpeg_command()?;
-        let args = get_command_args(&command).to_string_lossy().to_string();
+        let args = command_args(&config, &metadata)?;
         assert!(args.contains("-crf 25 -g 200"));
         assert!(args.contains("-vf fps=20"));
 
         Ok(())
     }
+
+    #[test]
+    fn limit_short_side() -> EncodeResult<()> {
+        let config = Config {
+            resolution: "720p".parse().unwrap(),
+            ..Config::default()
+        };
+
+        // 4:3 横屏：短边缩小到 720
+        let metadata = Metadata::new(1_440, 1_080, 24.0, 0.0, 0);
+        let encoder = Encoder::new(&config, &metadata, &capabilities())?;
+        assert_eq!(encoder.scaled_width(), Some(960));
+        assert_eq!(encoder.crf(), 25);
+
+        // 竖屏同样以短边为准
+        let metadata = Metadata::new(1_080, 1_920, 24.0, 0.0, 0);
+        let encoder = Encoder::new(&config, &metadata, &capabilities())?;
+        assert_eq!(encoder.scaled_height(), Some(1_280));
+
+        // 宽银幕的短边低于限制时不缩放
+        let metadata = Metadata::new(1_280, 536, 24.0, 0.0, 0);
+        let encoder = Encoder::new(&config, &metadata, &capabilities())?;
+        assert_eq!(
+            (encoder.scaled_width(), encoder.scaled_height()),
+            (None, None)
+        );
+        assert_eq!(encoder.crf(), 22);
+
+        // 4k 使用更高的 CRF
+        let config = Config {
+            resolution: "4k".parse().unwrap(),
+            ..Config::default()
+        };
+        let metadata = Metadata::new(7_680, 4_320, 24.0, 0.0, 0);
+        let encoder = Encoder::new(&config, &metadata, &capabilities())?;
+        assert_eq!(encoder.scaled_width(), Some(3_840));
+        assert_eq!(encoder.crf(), 28);
+
+        Ok(())
+    }
+
+    #[test]
+    fn scale_modes() -> EncodeResult<()> {
+        // 4:3 横屏源视频，限制为 720p
+        let metadata = Metadata::new(1_440, 1_080, 24.0, 0.0, 0);
+        let config = Config {
+            resolution: Resolution::Hd,
+            fps: FrameRate::integer(24).unwrap(),
+            ..Config::default()
+        };
+
+        let args = command_args(&config, &metadata)?;
+        assert!(args.contains("-vf scale=960:-2 "), "{}", args);
+
+        let config = config.with_scale_mode(ScaleMode::Exact);
+        let args = command_args(&config, &metadata)?;
+        assert!(args.contains("-vf scale=1280:720,setsar=1 "), "{}", args);
+
+        let config = config.with_scale_mode(ScaleMode::Crop);
+        let args = command_args(&config, &metadata)?;
+        assert!(
+            args.contains(
+                "-vf scale=1280:720:force_original_aspect_ratio=increase,crop=1280:720,setsar=1 "
+            ),
+            "{}",
+            args
+        );
+
+        let config = config.with_scale_mode(ScaleMode::Pad);
+        let args = command_args(&config, &metadata)?;
+        assert!(
+            args.contains("-vf scale=1280:720:force_original_aspect_ratio=decrease:force_divisible_by=2,pad=1280:720:-1:-1:color=black,setsar=1 "),
+            "{}",
+            args
+        );
+
+        // 竖屏的小视频同样放大到准确宽高，边框颜色可以配置
+        let metadata = Metadata::new(480, 854, 30.0, 0.0, 0);
+        let config = config.with_pad_color("#202020");
+        let args = command_args(&config, &metadata)?;
+        assert!(
+            args.contains("pad=720:1280:-1:-1:color=#202020,setsar=1,fps=24 "),
+            "{}",
+            args
+        );
+
+        // 奇数宽高取最接近的偶数
+        let metadata = Metadata::new(1_920, 1_080, 24.0, 0.0, 0);
+        let config = Config {
+            resolution: "1279x719".parse().unwrap(),
+            ..config
+        }
+        .with_scale_mode(ScaleMode::Exact);
+        let args = command_args(&config, &metadata)?;
+        assert!(args.contains("-vf scale=1280:720,setsar=1 "), "{}", args);
+
+        // 源视频已经是目标宽高时不缩放
+        let config = Config {
+            resolution: Resolution::Fhd,
+            ..config
+        };
+        let args = command_args(&config, &metadata)?;
+        assert!(!args.contains("-vf"), "{}", args);
+
+        // DVD 按 16:9 的显示宽高比裁剪与填充
+        let metadata = from_stream(dvd_stream());
+        let config = Config {
+            resolution: Resolution::Hd,
+            ..config
+        }
+        .with_scale_mode(ScaleMode::Crop);
+        let args = command_args(&config, &metadata)?;
+        assert!(
+            args.contains("-vf scale=iw*sar:ih,setsar=1,scale=1280:720:force_original_aspect_ratio=increase,crop=1280:720,setsar=1 "),
+            "{}",
+            args
+        );
+
+        let config = config.with_scale_mode(ScaleMode::Pad);
+        let args = command_args(&config, &metadata)?;
+        assert!(
+            args.contains(
+                "-vf scale=iw*sar:ih,setsar=1,scale=1280:720:force_original_aspect_ratio=decrease"
+            ),
+            "{}",
+            args
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn map_preset_to_svt_av1_level() -> EncodeResult<()> {
+        let metadata = Metadata::new(1_920, 1_080, 24.0, 0.0, 0);
+        let build = |preset: Preset| -> EncodeResult<String> {
+            let config = Config {
+                preset,
+                resolution: Resolution::Qhd,
+                ..Config::default()
+            };
+            command_args(&config, &metadata)
+        };
+
+        // 默认沿用此前固定的 -preset 4
+        assert!(build(Preset::default())?.contains("-preset 4 "));
+        assert!(build(Preset::Medium)?.contains("-preset 6 "));
+        assert!(build(Preset::Placebo)?.contains("-preset 0 "));
+        assert!(build("ultrafast".parse().unwrap())?.contains("-preset 13 "));
+        assert!(build("10".parse().unwrap())?.contains("-preset 10 "));
+
+        assert_eq!(
+            "14".parse::<Preset>(),
+            Err(crate::preset::PresetParseError::OutOfRange(14))
+        );
+        assert!("quick".parse::<Preset>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rotated_source_scale_displayed_axis() -> EncodeResult<()> {
+        // 手机竖拍：存储为 1920x1080，显示矩阵旋转 -90 度
+        let metadata = from_stream(Stream {
+            codec_type: CodecType::Video,
+            width: Some(1_920),
+            height: Some(1_080),
+            avg_frame_rate: Some("30/1".into()),
+            side_data_list: vec![SideData {
+                rotation: Some(-90.0),
+                ..SideData::default()
+            }],
+            ..Stream::default()
+        });
+        let config = Config {
+            resolution: Resolution::Hd,
+            fps: FrameRate::integer(24).unwrap(),
+            ..Config::default()
+        };
+
+        let args = command_args(&config, &metadata)?;
+        assert!(args.contains("-vf scale=-2:1280,fps=24"), "{}", args);
+
+        Ok(())
+    }
+
+    #[test]
+    fn anamorphic_to_square_pixels() -> EncodeResult<()> {
+        let metadata = from_stream(dvd_stream());
+
+        // 默认保留源视频的像素宽高比
+        let config = Config {
+            resolution: Resolution::Hd,
+            fps: FrameRate::integer(24).unwrap(),
+            ..Config::default()
+        };
+        let args = command_args(&config, &metadata)?;
+        assert!(!args.contains("-vf"), "{}", args);
+
+        let config = config.with_square_pixels(true);
+        let args = command_args(&config, &metadata)?;
+        assert!(args.contains("-vf scale=854:480,setsar=1"), "{}", args);
+
+        // 缩小时高度同样按显示宽高比计算
+        let config = Config {
+            resolution: Resolution::Arbitrary {
+                width: 640,
+                height: 360,
+            },
+            fps: FrameRate::integer(24).unwrap(),
+            ..Config::default()
+        }
+        .with_square_pixels(true);
+        let args = command_args(&config, &metadata)?;
+        assert!(args.contains("-vf scale=640:360,setsar=1"), "{}", args);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ntsc_source_keep_frame_rate() -> EncodeResult<()> {
+        // 23.976 低于 24，不需要降帧
+        let metadata = Metadata::new(1_920, 1_080, 23.976, 0.0, 0);
+        let config = Config {
+            resolution: Resolution::Qhd,
+            fps: FrameRate::integer(24).unwrap(),
+            ..Config::default()
+        };
+        let encoder = Encoder::new(&config, &metadata, &capabilities())?;
+        assert_eq!(encoder.fps(), None);
+        let args = command_args(&config, &metadata)?;
+        assert!(!args.contains("-vf"), "{}", args);
+        assert!(args.contains("-g 240"), "{}", args);
+
+        // 帧率限制同样可以是 NTSC 帧率
+        let metadata = Metadata::new(1_920, 1_080, 60.0, 0.0, 0);
+        let config = Config {
+            fps: "30000/1001".parse().unwrap(),
+            ..config
+        };
+        let args = command_args(&config, &metadata)?;
+        assert!(args.contains("-g 300"), "{}", args);
+        assert!(args.contains("-vf fps=30000/1001"), "{}", args);
+
+        Ok(())
+    }
+
+    #[test]
+    fn vfr_policy() -> EncodeResult<()> {
+        // 手机拍摄的可变帧率视频，最高 60 帧
+        let metadata = from_stream(Stream {
+            codec_type: CodecType::Video,
+            width: Some(1_920),
+            height: Some(1_080),
+            r_frame_rate: Some("60/1".into()),
+            avg_frame_rate: Some("5985000/199799".into()),
+            ..Stream::default()
+        });
+        let config = Config {
+            resolution: Resolution::Qhd,
+            fps: FrameRate::integer(30).unwrap(),
+            ..Config::default()
+        };
+
+        // 默认只限制最高帧率，不转为固定帧率
+        let args = command_args(&config, &metadata)?;
+        assert!(
+            args.contains("-g 300 -svtav1-params tune=0:film-grain=4 -fpsmax 30"),
+            "{}",
+            args
+        );
+        assert!(
+            !args.contains("-vf") && !args.contains("-fps_mode"),
+            "{}",
+            args
+        );
+
+        let config = config.with_vfr(Vfr::Keep);
+        let args = command_args(&config, &metadata)?;
+        assert!(
+            !args.contains("-fpsmax") && !args.contains("-vf"),
+            "{}",
+            args
+        );
+
+        let config = config.with_vfr(Vfr::Cfr);
+        let args = command_args(&config, &metadata)?;
+        assert!(args.contains("-fps_mode cfr"), "{}", args);
+        assert!(args.contains("-vf fps=30"), "{}", args);
+
+        Ok(())
+    }
+
+    /// 4k HDR10 视频流
+    fn hdr10_stream() -> Stream {
+        Stream {
+            codec_type: CodecType::Video,
+            width: Some(3_840),
+            height: Some(2_160),
+            avg_frame_rate: Some("24/1".into()),
+            pix_fmt: Some("yuv420p10le".into()),
+            color_primaries: Some("bt2020".into()),
+            color_transfer: Some("smpte2084".into()),
+            color_space: Some("bt2020nc".into()),
+            color_range: Some("tv".into()),
+            ..Stream::default()
+        }
+    }
+
+    #[test]
+    fn keep_hdr_signalling() -> EncodeResult<()> {
+        let metadata = from_stream(hdr10_stream());
+        let config = Config {
+            fps: FrameRate::integer(24).unwrap(),
+            ..Config::default()
+        };
+
+        let args = command_args(&config, &metadata)?;
+        assert!(
+            args.contains("-pix_fmt yuv420p10le -color_primaries bt2020 -color_trc smpte2084 -colorspace bt2020nc -color_range tv"),
+            "{}",
+            args
+        );
+        assert!(args.contains("-vf scale=1920:-2 "), "{}", args);
+
+        // SDR 源视频不设置色彩参数
+        let metadata = Metadata::new(1_920, 1_080, 24.0, 0.0, 0);
+        let args = command_args(&config, &metadata)?;
+        assert!(!args.contains("-color"), "{}", args);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tonemap_to_sdr() -> EncodeResult<()> {
+        let metadata = from_stream(hdr10_stream());
+        let config = Config {
+            fps: FrameRate::integer(24).unwrap(),
+            ..Config::default()
+        }
+        .with_tonemap(Tonemap::Sdr);
+
+        let args = command_args(&config, &metadata)?;
+        assert!(
+            args.contains("-color_primaries bt709 -color_trc bt709 -colorspace bt709"),
+            "{}",
+            args
+        );
+        assert!(
+            args.contains("-vf scale=1920:-2,zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,tonemap=tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p"),
+            "{}",
+            args
+        );
+        assert!(!args.contains("10le"));
+
+        // 缺少 zscale 时提前报错
+        let capabilities =
+            Capabilities::new(["libsvtav1"], ["scale", "format", "tonemap"], ["mp4"]);
+        let result = Encoder::new(&config, &metadata, &capabilities);
+        assert!(matches!(
+            result,
+            Err(EncoderError::Ffmpeg(FfmpegError::MissingFilter(_)))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_unsupported_ffmpeg() {
+        let metadata = Metadata::new(1_920, 1_080, 30.0, 0.0, 0);
+        let config = Config {
+            resolution: Resolution::Hd,
+            fps: FrameRate::integer(24).unwrap(),
+            ..Config::default()
+        };
+
+        assert!(Encoder::check_capabilities(&capabilities(), "mp4").is_ok());
+
+        let capabilities = Capabilities::new(["libx264"], REQUIRED_FILTERS, ["mp4"]);
+        let result = Encoder::check_capabilities(&capabilities, "mp4");
+        assert!(matches!(
+            result,
+            Err(EncoderError::Ffmpeg(FfmpegError::MissingEncoder(_)))
+        ));
+
+        let capabilities = Capabilities::new(["libsvtav1"], ["fps"], ["mp4"]);
+        let result = Encoder::check_capabilities(&capabilities, "mp4");
+        assert!(matches!(
+            result,
+            Err(EncoderError::Ffmpeg(FfmpegError::MissingFilter(_)))
+        ));
+
+        let capabilities = Capabilities::new(["libsvtav1"], REQUIRED_FILTERS, ["mp4"]);
+        let result = Encoder::check_capabilities(&capabilities, "mkv");
+        assert!(matches!(
+            result,
+            Err(EncoderError::Ffmpeg(FfmpegError::MissingMuxer(_)))
+        ));
+
+        // 固定使用的滤镜已提前检查，逐个视频只检查裁剪等按需使用的滤镜
+        assert!(Encoder::new(&config, &metadata, &capabilities).is_ok());
+        let config = config.with_scale_mode(ScaleMode::Crop);
+        let result = Encoder::new(&config, &metadata, &capabilities);
+        assert!(matches!(
+            result,
+            Err(EncoderError::Ffmpeg(FfmpegError::MissingFilter(_)))
+        ));
+    }
 }
@@ -1,3 +1,4 @@
+use ffmpeg_command_builder::FfmpegError;
 use ffmpeg_progress_monitor::ProgressMonitorError;
 use std::io;
 use video_metadata::ResolutionError;
@@ -12,10 +13,12 @@ pub enum EncoderError {
     ProgressMonitor(#[from] ProgressMonitorError),
     #[error(transparent)]
     IO(#[from] io::Error),
-    #[error("failed to get stderr")]
+    #[error(transparent)]
+    Ffmpeg(#[from] FfmpegError),
+    #[error("failed to get stdout or stderr of ffmpeg")]
     TakeStd,
-    #[error("FFmpeg exited with status {0}")]
-    FfmpegExit(String),
+    #[error("FFmpeg failed ({status}): {log}")]
+    FfmpegExit { status: String, log: String },
 }
 
 pub(crate) type EncodeResult<T> = Result<T, EncoderError>;
@@ -2,8 +2,14 @@ mod config;
 mod encoder;
 mod error;
 mod preset;
+mod scale_mode;
+mod tonemap;
+mod vfr;
 
 pub use config::Config;
 pub use encoder::Encoder;
 pub use error::EncoderError;
 pub use preset::Preset;
+pub use scale_mode::ScaleMode;
+pub use tonemap::Tonemap;
+pub use vfr::Vfr;
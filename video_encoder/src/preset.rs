@@ -1,31 +1,56 @@
 use std::fmt;
 use std::str::FromStr;
 
+/// 编码预设，名称沿用 x264/x265，映射到 SVT-AV1 的 0（最慢）到 13（最快）
+///
 /// https://x265.readthedocs.io/en/master/presets.html#presets
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// https://gitlab.com/AOMediaCodec/SVT-AV1/-/blob/master/Docs/CommonQuestions.md#what-presets-do
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum Preset {
-    // Ultrafast,
-    // Superfast,
+    Ultrafast,
+    Superfast,
     Veryfast,
     Faster,
     Fast,
     Medium,
+    /// SVT-AV1 的 4，此前固定使用的预设
+    #[default]
     Slow,
     Slower,
     Veryslow,
-    // Placebo,
+    Placebo,
+    /// 直接指定 SVT-AV1 的预设，取值 0 到 13
+    Level(u8),
 }
 
-impl Default for Preset {
-    fn default() -> Self {
-        Self::Medium
-    }
-}
+/// SVT-AV1 预设的最大值
+const MAX_LEVEL: u8 = 13;
 
 #[derive(Debug, thiserror::Error, PartialEq)]
 pub enum PresetParseError {
     #[error("no such preset: {0}")]
     NoSuchPreset(String),
+    #[error("preset level {0} is out of range 0-13")]
+    OutOfRange(u8),
+}
+
+impl Preset {
+    /// 对应的 SVT-AV1 预设
+    pub fn svt_av1_level(&self) -> u8 {
+        match self {
+            Preset::Ultrafast => 13,
+            Preset::Superfast => 12,
+            Preset::Veryfast => 11,
+            Preset::Faster => 10,
+            Preset::Fast => 8,
+            Preset::Medium => 6,
+            Preset::Slow => 4,
+            Preset::Slower => 3,
+            Preset::Veryslow => 2,
+            Preset::Placebo => 0,
+            Preset::Level(level) => *level,
+        }
+    }
 }
 
 impl FromStr for Preset {
@@ -33,6 +58,8 @@ impl FromStr for Preset {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
+            "ultrafast" => Ok(Self::Ultrafast),
+            "superfast" => Ok(Self::Superfast),
             "veryfast" => Ok(Self::Veryfast),
             "faster" => Ok(Self::Faster),
             "fast" => Ok(Self::Fast),
@@ -40,7 +67,12 @@ impl FromStr for Preset {
             "slow" => Ok(Self::Slow),
             "slower" => Ok(Self::Slower),
             "veryslow" => Ok(Self::Veryslow),
-            _ => Err(PresetParseError::NoSuchPreset(s.to_string())),
+            "placebo" => Ok(Self::Placebo),
+            _ => match s.parse::<u8>() {
+                Ok(level) if level <= MAX_LEVEL => Ok(Self::Level(level)),
+                Ok(level) => Err(PresetParseError::OutOfRange(level)),
+                Err(_) => Err(PresetParseError::NoSuchPreset(s.to_string())),
+            },
         }
     }
 }
@@ -48,6 +80,8 @@ impl FromStr for Preset {
 impl fmt::Display for Preset {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Preset::Ultrafast => write!(f, "ultrafast"),
+            Preset::Superfast => write!(f, "superfast"),
             Preset::Veryfast => write!(f, "veryfast"),
             Preset::Faster => write!(f, "faster"),
             Preset::Fast => write!(f, "fast"),
@@ -55,6 +89,8 @@ impl fmt::Display for Preset {
             Preset::Slow => write!(f, "slow"),
             Preset::Slower => write!(f, "slower"),
             Preset::Veryslow => write!(f, "veryslow"),
+            Preset::Placebo => write!(f, "placebo"),
+            Preset::Level(level) => write!(f, "{}", level),
         }
     }
 }
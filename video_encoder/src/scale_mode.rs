@@ -0,0 +1,49 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// 源视频与分辨率限制宽高比不同时的缩放方式
+///
+/// 除 `Fit` 外都输出分辨率限制的准确宽高（按源视频朝向），源视频较小时也会放大
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ScaleMode {
+    /// 保持宽高比，只把短边缩小到限制，不放大
+    #[default]
+    Fit,
+    /// 拉伸到准确宽高，不保持宽高比
+    Exact,
+    /// 保持宽高比放大到填满，再居中裁剪多余部分
+    Crop,
+    /// 保持宽高比缩放到完整放入，再居中填充边框（信箱或柱状框）
+    Pad,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum ScaleModeParseError {
+    #[error("no such scale mode: {0}")]
+    NoSuchScaleMode(String),
+}
+
+impl FromStr for ScaleMode {
+    type Err = ScaleModeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fit" => Ok(Self::Fit),
+            "exact" => Ok(Self::Exact),
+            "crop" => Ok(Self::Crop),
+            "pad" => Ok(Self::Pad),
+            _ => Err(ScaleModeParseError::NoSuchScaleMode(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ScaleMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScaleMode::Fit => write!(f, "fit"),
+            ScaleMode::Exact => write!(f, "exact"),
+            ScaleMode::Crop => write!(f, "crop"),
+            ScaleMode::Pad => write!(f, "pad"),
+        }
+    }
+}
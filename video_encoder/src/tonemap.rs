@@ -0,0 +1,39 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// HDR 源视频的处理方式，SDR 源视频不受影响
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Tonemap {
+    /// 保留 HDR 信号，输出 10bit 并携带源视频的色彩标记
+    #[default]
+    Keep,
+    /// 用 CPU 滤镜（zscale + tonemap）映射到 BT.709 SDR
+    Sdr,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum TonemapParseError {
+    #[error("no such tonemap mode: {0}")]
+    NoSuchTonemap(String),
+}
+
+impl FromStr for Tonemap {
+    type Err = TonemapParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "sdr" => Ok(Self::Sdr),
+            _ => Err(TonemapParseError::NoSuchTonemap(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Tonemap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tonemap::Keep => write!(f, "keep"),
+            Tonemap::Sdr => write!(f, "sdr"),
+        }
+    }
+}
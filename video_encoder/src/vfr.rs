@@ -0,0 +1,43 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// 可变帧率源视频的处理方式，固定帧率的源视频只在超过帧率限制时降帧
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Vfr {
+    /// 保留源视频的时间戳，忽略帧率限制
+    Keep,
+    /// 保留可变帧率，只丢弃超过帧率限制的帧（`-fpsmax`）
+    #[default]
+    Cap,
+    /// 转为固定帧率，取基础帧率与帧率限制中的较小值
+    Cfr,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum VfrParseError {
+    #[error("no such vfr mode: {0}")]
+    NoSuchVfr(String),
+}
+
+impl FromStr for Vfr {
+    type Err = VfrParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "cap" => Ok(Self::Cap),
+            "cfr" => Ok(Self::Cfr),
+            _ => Err(VfrParseError::NoSuchVfr(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Vfr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Vfr::Keep => write!(f, "keep"),
+            Vfr::Cap => write!(f, "cap"),
+            Vfr::Cfr => write!(f, "cfr"),
+        }
+    }
+}
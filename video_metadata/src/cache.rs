@@ -0,0 +1,307 @@
+use crate::{Metadata, MetadataError, Probe};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// 缓存文件格式变化时递增，旧版本的缓存直接丢弃
+///
+/// 2：增加 nb_read_packets，旧缓存中缺少时长的视频需要重新统计数据包
+const VERSION: u32 = 2;
+
+/// 持久化的视频信息缓存，避免每次运行都对所有文件调用 ffprobe
+///
+/// 以规范化路径为键，文件大小或修改时间变化时视为失效
+#[derive(Debug, Default)]
+pub struct MetadataCache {
+    /// 为 `None` 时不读写磁盘，每次都调用 ffprobe
+    path: Option<PathBuf>,
+    entries: HashMap<String, Entry>,
+    dirty: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: HashMap<String, Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    size: u64,
+    mtime_secs: u64,
+    mtime_nanos: u32,
+    probe: Probe,
+}
+
+impl MetadataCache {
+    /// 缓存文件的默认位置：$XDG_CACHE_HOME/noobtool/metadata.json
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))?;
+        Some(dir.join("noobtool").join("metadata.json"))
+    }
+
+    /// 读取缓存文件，文件不存在或无法解析时从空缓存开始
+    pub fn open<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        let entries = fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<CacheFile>(&data).ok())
+            .filter(|file| file.version == VERSION)
+            .map(|file| file.entries)
+            .unwrap_or_default();
+
+        Self {
+            path: Some(path),
+            entries,
+            dirty: false,
+        }
+    }
+
+    /// 不使用缓存
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// 优先使用缓存，缓存缺失或失效时调用 ffprobe 并更新缓存
+    pub fn retrive(&mut self, video: &Path) -> Result<Metadata, MetadataError> {
+        if self.path.is_none() {
+            return Metadata::retrive(video);
+        }
+
+        let Ok((key, stamp)) = stamp(video) else {
+            // 无法读取文件信息时交给 ffprobe 报告错误
+            return Metadata::retrive(video);
+        };
+
+        if let Some(metadata) = self.lookup(&key, &stamp) {
+            return Ok(metadata);
+        }
+
+        let metadata = Metadata::retrive(video)?;
+        self.entries
+            .insert(key, stamp.entry(metadata.probe().clone()));
+        self.dirty = true;
+        Ok(metadata)
+    }
+
+    /// 只需要宽高、时长等基本信息时使用，启用 `native` 时在缓存缺失的情况下优先解析文件头
+    ///
+    /// 文件头中没有色彩与 HDR 信息，解析结果不写入缓存；无法解析时与 [`MetadataCache::retrive`] 相同
+    pub fn scan(&mut self, video: &Path) -> Result<Metadata, MetadataError> {
+        #[cfg(feature = "native")]
+        {
+            let cached = stamp(video)
+                .ok()
+                .and_then(|(key, stamp)| self.lookup(&key, &stamp));
+            if let Some(metadata) = cached.or_else(|| Metadata::read_header(video)) {
+                return Ok(metadata);
+            }
+        }
+
+        self.retrive(video)
+    }
+
+    /// 文件未变化时取出缓存的结果
+    fn lookup(&self, key: &str, stamp: &Stamp) -> Option<Metadata> {
+        let entry = self.entries.get(key).filter(|entry| stamp.matches(entry))?;
+        Metadata::from_probe(entry.probe.clone()).ok()
+    }
+
+    /// 有新的结果时写回磁盘
+    pub fn save(&mut self) -> io::Result<()> {
+        let Some(path) = self.path.as_deref().filter(|_| self.dirty) else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let file = CacheFile {
+            version: VERSION,
+            entries: std::mem::take(&mut self.entries),
+        };
+        let data = serde_json::to_vec(&file)?;
+        self.entries = file.entries;
+
+        // 先写临时文件再重命名，避免中断时留下损坏的缓存
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, path)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// 删除缓存文件，返回是否确实删除了文件
+    pub fn clear(path: &Path) -> io::Result<bool> {
+        match fs::remove_file(path) {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// 用于判断缓存是否失效的文件信息
+struct Stamp {
+    size: u64,
+    mtime_secs: u64,
+    mtime_nanos: u32,
+}
+
+impl Stamp {
+    fn matches(&self, entry: &Entry) -> bool {
+        self.size == entry.size
+            && self.mtime_secs == entry.mtime_secs
+            && self.mtime_nanos == entry.mtime_nanos
+    }
+
+    fn entry(self, probe: Probe) -> Entry {
+        Entry {
+            size: self.size,
+            mtime_secs: self.mtime_secs,
+            mtime_nanos: self.mtime_nanos,
+            probe,
+        }
+    }
+}
+
+fn stamp(video: &Path) -> io::Result<(String, Stamp)> {
+    let key = fs::canonicalize(video)?.to_string_lossy().into_owned();
+    let file = fs::metadata(video)?;
+    let mtime = file
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_err(io::Error::other)?;
+
+    Ok((
+        key,
+        Stamp {
+            size: file.len(),
+            mtime_secs: mtime.as_secs(),
+            mtime_nanos: mtime.subsec_nanos(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 测试用的临时目录，结束时删除
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("noobtool-{}-{}", name, std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn hit_until_file_changes() -> io::Result<()> {
+        let dir = TempDir::new("cache");
+        let video = dir.0.join("a.mp4");
+        let cache_path = dir.0.join("cache").join("metadata.json");
+        fs::write(&video, b"0123")?;
+
+        let probe: Probe = serde_json::from_str(include_str!("../ffprobe.json"))?;
+        let mut cache = MetadataCache::open(&cache_path);
+        let (key, stamp) = stamp(&video)?;
+        cache.entries.insert(key, stamp.entry(probe.clone()));
+        cache.dirty = true;
+        cache.save()?;
+
+        // 重新读取后命中缓存，不会调用 ffprobe
+        let mut cache = MetadataCache::open(&cache_path);
+        assert_eq!(cache.len(), 1);
+        let metadata = cache.retrive(&video).unwrap();
+        assert_eq!(metadata.probe(), &probe);
+
+        // 文件大小变化后缓存失效
+        fs::write(&video, b"01234")?;
+        let (key, stamp) = super::stamp(&video)?;
+        assert!(!stamp.matches(&cache.entries[&key]));
+
+        assert!(MetadataCache::clear(&cache_path)?);
+        assert!(!MetadataCache::clear(&cache_path)?);
+        assert!(MetadataCache::open(&cache_path).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn ignore_corrupt_cache() -> io::Result<()> {
+        let dir = TempDir::new("corrupt");
+        let cache_path = dir.0.join("metadata.json");
+        fs::write(&cache_path, b"not json")?;
+
+        assert!(MetadataCache::open(&cache_path).is_empty());
+        Ok(())
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn scan_header_without_caching() -> io::Result<()> {
+        let dir = TempDir::new("scan");
+        let cache_path = dir.0.join("metadata.json");
+        let video = dir.0.join("a.mp4");
+        fs::write(&video, crate::native::mp4_fixture([1, 0]))?;
+
+        // 文件头中的信息足够扫描使用，但缺少色彩信息，不能留在缓存中给编码使用
+        let mut cache = MetadataCache::open(&cache_path);
+        let metadata = cache.scan(&video).unwrap();
+        assert_eq!((metadata.width(), metadata.height()), (1920, 1080));
+        assert!(cache.is_empty());
+
+        // 缓存中已有完整结果时优先使用
+        let probe: Probe = serde_json::from_str(include_str!("../ffprobe.json"))?;
+        let (key, stamp) = stamp(&video)?;
+        cache.entries.insert(key, stamp.entry(probe.clone()));
+        assert_eq!(cache.scan(&video).unwrap().probe(), &probe);
+
+        Ok(())
+    }
+
+    #[test]
+    fn discard_old_version() -> io::Result<()> {
+        let dir = TempDir::new("version");
+        let cache_path = dir.0.join("metadata.json");
+        let video = dir.0.join("a.ts");
+        fs::write(&video, b"0123")?;
+
+        let probe: Probe = serde_json::from_str(include_str!("../ffprobe.json"))?;
+        let (key, stamp) = stamp(&video)?;
+        let file = CacheFile {
+            version: VERSION - 1,
+            entries: [(key, stamp.entry(probe))].into(),
+        };
+        fs::write(&cache_path, serde_json::to_vec(&file)?)?;
+
+        assert!(MetadataCache::open(&cache_path).is_empty());
+        Ok(())
+    }
+}
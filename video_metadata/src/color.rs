@@ -0,0 +1,58 @@
+use std::fmt;
+
+/// HDR 的传输特性
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HdrFormat {
+    /// PQ（smpte2084），HDR10 与杜比视界的基础层
+    Pq,
+    /// HLG（arib-std-b67），常见于广播与手机拍摄
+    Hlg,
+}
+
+impl HdrFormat {
+    /// 根据 ffprobe 的 color_transfer 判断
+    pub fn from_transfer(transfer: &str) -> Option<Self> {
+        match transfer {
+            "smpte2084" => Some(Self::Pq),
+            "arib-std-b67" => Some(Self::Hlg),
+            _ => None,
+        }
+    }
+
+    /// ffmpeg `-color_trc` 的取值
+    pub fn transfer(&self) -> &'static str {
+        match self {
+            HdrFormat::Pq => "smpte2084",
+            HdrFormat::Hlg => "arib-std-b67",
+        }
+    }
+}
+
+impl fmt::Display for HdrFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HdrFormat::Pq => write!(f, "HDR10"),
+            HdrFormat::Hlg => write!(f, "HLG"),
+        }
+    }
+}
+
+/// 视频的色彩信息，取值与 ffmpeg 的 `-color_primaries`、`-color_trc`、`-colorspace` 一致
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ColorInfo {
+    /// 例如：bt709、bt2020
+    pub primaries: Option<String>,
+    /// 例如：bt709、smpte2084、arib-std-b67
+    pub transfer: Option<String>,
+    /// 例如：bt709、bt2020nc
+    pub space: Option<String>,
+    /// tv 或 pc
+    pub range: Option<String>,
+}
+
+impl ColorInfo {
+    /// 仅按传输特性判断，BT.2020 色域的 SDR 视频不算 HDR
+    pub fn hdr(&self) -> Option<HdrFormat> {
+        HdrFormat::from_transfer(self.transfer.as_deref()?)
+    }
+}
@@ -0,0 +1,138 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+/// 以分数表示的精确帧率，例如 NTSC 的 30000/1001
+///
+/// 总是约分后保存，因此 `60/2` 与 `30/1` 相等
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct FrameRate {
+    num: u32,
+    den: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum FrameRateError {
+    #[error("frame rate can't be zero")]
+    Zero,
+    #[error("error parsing {0}")]
+    Parse(String),
+}
+
+impl FrameRate {
+    pub fn new(num: u32, den: u32) -> Result<Self, FrameRateError> {
+        if num == 0 || den == 0 {
+            return Err(FrameRateError::Zero);
+        }
+        let divisor = gcd(num, den);
+        Ok(Self {
+            num: num / divisor,
+            den: den / divisor,
+        })
+    }
+
+    /// 整数帧率，例如：24
+    pub fn integer(fps: u32) -> Result<Self, FrameRateError> {
+        Self::new(fps, 1)
+    }
+
+    /// 小数帧率，接近 NTSC 帧率（N*1000/1001）时取精确值，其他按千分之一精度近似
+    pub fn from_f32(fps: f32) -> Result<Self, FrameRateError> {
+        if !fps.is_finite() || fps <= 0.0 {
+            return Err(FrameRateError::Zero);
+        }
+
+        let fps = fps as f64;
+        let ntsc = (fps * 1.001).round();
+        if (fps - fps.round()).abs() >= 0.001 && (fps * 1.001 - ntsc).abs() < 0.005 {
+            return Self::new(ntsc as u32 * 1_000, 1_001);
+        }
+        Self::new((fps * 1_000.0).round() as u32, 1_000)
+    }
+
+    pub fn num(&self) -> u32 {
+        self.num
+    }
+
+    pub fn den(&self) -> u32 {
+        self.den
+    }
+
+    pub fn as_f32(&self) -> f32 {
+        (self.num as f64 / self.den as f64) as f32
+    }
+}
+
+impl Default for FrameRate {
+    fn default() -> Self {
+        Self { num: 24, den: 1 }
+    }
+}
+
+impl Ord for FrameRate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.num as u64 * other.den as u64).cmp(&(other.num as u64 * self.den as u64))
+    }
+}
+
+impl PartialOrd for FrameRate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// 接受 `30000/1001`、`24` 与 `29.97` 三种写法
+impl FromStr for FrameRate {
+    type Err = FrameRateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_error = || FrameRateError::Parse(s.to_string());
+        match s.trim().split_once('/') {
+            Some((num, den)) => Self::new(
+                num.parse().map_err(|_| parse_error())?,
+                den.parse().map_err(|_| parse_error())?,
+            ),
+            None => match s.trim().parse::<u32>() {
+                Ok(fps) => Self::integer(fps),
+                Err(_) => Self::from_f32(s.trim().parse().map_err(|_| parse_error())?),
+            },
+        }
+    }
+}
+
+impl fmt::Display for FrameRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.den {
+            1 => write!(f, "{}", self.num),
+            den => write!(f, "{}/{}", self.num, den),
+        }
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    match b {
+        0 => a,
+        b => gcd(b, a % b),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_and_compare() -> Result<(), FrameRateError> {
+        let ntsc = FrameRate::new(24_000, 1_001)?;
+        assert_eq!("24000/1001".parse(), Ok(ntsc));
+        assert_eq!("23.976".parse(), Ok(ntsc));
+        assert_eq!("48/2".parse(), Ok(FrameRate::integer(24)?));
+        assert_eq!("12.5".parse(), Ok(FrameRate::new(25, 2)?));
+        assert_eq!("0/0".parse::<FrameRate>(), Err(FrameRateError::Zero));
+        assert!("abc".parse::<FrameRate>().is_err());
+
+        assert!(ntsc < FrameRate::integer(24)?);
+        assert!(FrameRate::new(30_000, 1_001)? > FrameRate::integer(24)?);
+        assert_eq!(ntsc.to_string(), "24000/1001");
+        assert_eq!(FrameRate::integer(24)?.to_string(), "24");
+
+        Ok(())
+    }
+}
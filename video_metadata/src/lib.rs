@@ -1,5 +1,17 @@
+mod cache;
+mod color;
+mod frame_rate;
 mod metadata;
+#[cfg(feature = "native")]
+mod native;
+mod probe;
 mod resolution;
+mod source;
 
+pub use cache::MetadataCache;
+pub use color::{ColorInfo, HdrFormat};
+pub use frame_rate::{FrameRate, FrameRateError};
 pub use metadata::{Metadata, MetadataError};
+pub use probe::{CodecType, Format, Probe, SideData, Stream};
 pub use resolution::{Orientation, Resolution, ResolutionError};
+pub use source::{Source, Sources};
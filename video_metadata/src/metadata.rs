@@ -1,34 +1,31 @@
-use crate::{Resolution, ResolutionError};
-use std::{
-    fmt, io,
-    num::{ParseFloatError, ParseIntError},
-    path::Path,
-    process::Command,
-    string::FromUtf8Error,
+use crate::{
+    CodecType, ColorInfo, Format, FrameRate, HdrFormat, Orientation, Probe, Resolution,
+    ResolutionError, Source, Sources, Stream,
 };
-use utils::parse_fraction;
+use ffmpeg_command_builder::Binaries;
+use std::{fmt, fs, io, path::Path, process::Command};
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+/// 视频信息，基于 ffprobe 的完整输出，访问方法取自第一个视频流（不含封面图）与封装格式
+#[derive(Debug, PartialEq, Clone)]
 pub struct Metadata {
-    width: u16,
-    height: u16,
-    /// 平均帧率
-    fps: f32,
-    /// 时长，单位秒
-    duration: f32,
-    /// 文件大小，单位字节
-    size: u64,
+    probe: Probe,
+    /// 主视频流在 `probe.streams` 中的位置
+    video: usize,
+    /// 以下信息按优先级从多个来源中选出，见 [`Source`]
+    duration: Option<(f32, Source)>,
+    frame_rate: (FrameRate, Source),
+    size: (u64, Source),
 }
 
 impl Default for Metadata {
     fn default() -> Self {
-        Self {
-            width: 1_920,
-            height: 1_080,
-            fps: Default::default(),
-            duration: Default::default(),
-            size: Default::default(),
-        }
+        Self::new(
+            1_920,
+            1_080,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )
     }
 }
 
@@ -38,12 +35,8 @@ pub enum MetadataError {
     IO(#[from] io::Error),
     #[error("Ffprobe error: {0}")]
     Ffprobe(String),
-    #[error(transparent)]
-    ReadStdout(#[from] FromUtf8Error),
-    #[error(transparent)]
-    ParseInt(#[from] ParseIntError),
-    #[error(transparent)]
-    ParseFloat(#[from] ParseFloatError),
+    #[error("failed to parse ffprobe output: {0}")]
+    Json(#[from] serde_json::Error),
     #[error("no such data: {0}")]
     NoSuchData(String),
 }
@@ -53,117 +46,508 @@ impl fmt::Display for Metadata {
         write!(
             f,
             "{}x{}, {}fps, {}s, {}byte",
-            self.width, self.height, self.fps, self.duration, self.size
+            self.width(),
+            self.height(),
+            self.fps(),
+            self.duration(),
+            self.size()
         )
     }
 }
 
 impl Metadata {
-    /// 用于测试
+    /// 用于测试，构造只有一个视频流的信息
     pub fn new(width: u16, height: u16, fps: f32, duration: f32, size: u64) -> Self {
+        let frame_rate = FrameRate::from_f32(fps).unwrap_or_default();
+        let video = Stream {
+            codec_type: CodecType::Video,
+            width: Some(width),
+            height: Some(height),
+            avg_frame_rate: Some(format!("{}/{}", frame_rate.num(), frame_rate.den())),
+            ..Stream::default()
+        };
+        let format = Format {
+            nb_streams: Some(1),
+            duration: Some(duration),
+            size: Some(size),
+            ..Format::default()
+        };
+
         Self {
-            width,
-            height,
-            fps,
-            duration,
-            size,
+            probe: Probe {
+                streams: vec![video],
+                format,
+            },
+            video: 0,
+            duration: Some((duration, Source::Format)),
+            frame_rate: (frame_rate, Source::Stream),
+            size: (size, Source::Format),
         }
     }
 
     pub fn retrive(video: &Path) -> Result<Self, MetadataError> {
-        // ffprobe -v fatal -select_streams v:0 -show_entries stream=width,height,avg_frame_rate -show_entries format=duration,size -of default=noprint_wrappers=1 input.mp4
-        let output = Command::new("ffprobe")
-            .args([
-                "-v",
-                "fatal",
+        // ffprobe -v error -of json -show_streams -show_format input.mp4
+        let output = ffprobe(video, &["-show_streams", "-show_format"])?;
+        let metadata = Self::from_probe(serde_json::from_slice(&output)?)?;
+        if metadata.duration.is_some() {
+            return Ok(metadata);
+        }
+
+        // 其他来源都没有时长，统计数据包数量推算，失败时仍按未知时长处理
+        // ffprobe -v error -of json -select_streams 0 -count_packets -show_entries stream=nb_read_packets input.ts
+        let index = metadata.video_stream().index.to_string();
+        let packets = ffprobe(
+            video,
+            &[
                 "-select_streams",
-                "v:0",
+                &index,
+                "-count_packets",
                 "-show_entries",
-                "stream=width,height,avg_frame_rate",
-                "-show_entries",
-                "format=duration,size",
-                "-of",
-                "default=noprint_wrappers=1",
-            ])
-            .arg(video)
-            .output()?;
-
-        if !output.status.success() {
-            let error_msg = if output.stderr.is_empty() {
-                format!("Ffprobe exited with status {}", output.status)
-            } else {
-                String::from_utf8_lossy(&output.stderr).into_owned()
-            };
-            return Err(MetadataError::Ffprobe(error_msg));
-        }
+                "stream=nb_read_packets",
+            ],
+        )
+        .ok()
+        .and_then(|output| serde_json::from_slice::<Probe>(&output).ok())
+        .and_then(|probe| probe.streams.first()?.nb_read_packets);
 
-        let out_str = String::from_utf8(output.stdout)?;
-
-        let mut width = None;
-        let mut height = None;
-        let mut fps = None;
-        let mut duration = None;
-        let mut size = None;
-
-        for line in out_str.lines() {
-            match line {
-                s if s.starts_with("width=") => {
-                    width = Some(line.trim_start_matches("width=").parse::<u16>()?)
-                }
-                s if s.starts_with("height=") => {
-                    height = Some(line.trim_start_matches("height=").parse::<u16>()?)
-                }
-                s if s.starts_with("avg_frame_rate=") => {
-                    fps = parse_fraction(line.trim_start_matches("avg_frame_rate="))
-                }
-                s if s.starts_with("duration=") => {
-                    duration = Some(line.trim_start_matches("duration=").parse::<f32>()?)
-                }
-                s if s.starts_with("size=") => {
-                    size = Some(line.trim_start_matches("size=").parse::<u64>()?)
-                }
-                _ => (),
-            };
-        }
+        let mut probe = metadata.probe;
+        probe.streams[metadata.video].nb_read_packets = packets;
+        Self::from_probe(probe)
+    }
+
+    /// 优先直接解析 MP4/MOV 与 Matroska/WebM 的文件头，失败时回退到 ffprobe
+    ///
+    /// 文件头只包含宽高、时长、帧率、编码等基本信息，适合扫描大量文件；
+    /// 色彩与 HDR 信息缺失，编码前仍应使用 [`Metadata::retrive`]
+    #[cfg(feature = "native")]
+    pub fn scan(video: &Path) -> Result<Self, MetadataError> {
+        Self::read_header(video).map_or_else(|| Self::retrive(video), Ok)
+    }
+
+    /// 只解析文件头，不支持的容器或信息不全时返回 `None`
+    #[cfg(feature = "native")]
+    pub(crate) fn read_header(video: &Path) -> Option<Self> {
+        crate::native::read(video)
+            .ok()
+            .and_then(|probe| Self::from_probe(probe).ok())
+    }
+
+    /// 从 ffprobe 的输出中选出主视频流，并确认编码所需的信息齐全
+    pub fn from_probe(probe: Probe) -> Result<Self, MetadataError> {
+        let video = probe
+            .streams
+            .iter()
+            .position(|stream| stream.is_video() && !stream.is_attached_pic())
+            .ok_or_else(|| MetadataError::NoSuchData("video stream".into()))?;
+
+        let stream = &probe.streams[video];
+        stream
+            .width
+            .ok_or_else(|| MetadataError::NoSuchData("width".into()))?;
+        stream
+            .height
+            .ok_or_else(|| MetadataError::NoSuchData("height".into()))?;
+        let frame_rate =
+            resolve_frame_rate(stream).ok_or_else(|| MetadataError::NoSuchData("fps".into()))?;
+        let size =
+            resolve_size(&probe.format).ok_or_else(|| MetadataError::NoSuchData("size".into()))?;
+        // 没有时长的视频仍然可以按帧数显示进度，因此时长可以缺失
+        let duration = resolve_duration(&probe, video, frame_rate.0);
+
+        Ok(Self {
+            probe,
+            video,
+            duration,
+            frame_rate,
+            size,
+        })
+    }
+
+    pub fn probe(&self) -> &Probe {
+        &self.probe
+    }
+
+    /// 所有流，包括音频、字幕与附件
+    pub fn streams(&self) -> &[Stream] {
+        &self.probe.streams
+    }
 
-        let width = width.ok_or_else(|| MetadataError::NoSuchData("width".into()))?;
-        let height = height.ok_or_else(|| MetadataError::NoSuchData("height".into()))?;
-        let fps = fps.ok_or_else(|| MetadataError::NoSuchData("fps".into()))?;
-        let duration = duration.ok_or_else(|| MetadataError::NoSuchData("duration".into()))?;
-        let size = size.ok_or_else(|| MetadataError::NoSuchData("size".into()))?;
+    pub fn format(&self) -> &Format {
+        &self.probe.format
+    }
+
+    /// 主视频流
+    pub fn video_stream(&self) -> &Stream {
+        &self.probe.streams[self.video]
+    }
 
-        Ok(Metadata::new(width, height, fps, duration, size))
+    /// 某一类型的所有流
+    pub fn streams_of(&self, codec_type: CodecType) -> impl Iterator<Item = &Stream> {
+        self.streams()
+            .iter()
+            .filter(move |stream| stream.codec_type == codec_type)
     }
 
     pub fn width(&self) -> u16 {
-        self.width
+        self.video_stream().width.unwrap_or_default()
     }
 
     pub fn height(&self) -> u16 {
-        self.height
+        self.video_stream().height.unwrap_or_default()
+    }
+
+    /// 播放时需要顺时针旋转的角度，取值 0、90、180、270
+    pub fn rotation(&self) -> u16 {
+        self.video_stream().rotation()
+    }
+
+    /// 像素宽高比（SAR），未知时视为方形像素
+    pub fn sar(&self) -> f32 {
+        self.video_stream().sar().unwrap_or(1.0)
+    }
+
+    /// 按方形像素换算、旋转前的显示宽高
+    ///
+    /// 非方形像素（例如 DVD 的 720x480，SAR 8:9）按 SAR 拉伸宽度
+    fn upright_size(&self) -> (u16, u16) {
+        let width = (self.width() as f32 * self.sar()).round() as u16;
+        (width, self.height())
+    }
+
+    /// 播放时显示的宽度，已考虑像素宽高比与旋转
+    pub fn display_width(&self) -> u16 {
+        let (width, height) = self.upright_size();
+        match self.rotation() {
+            90 | 270 => height,
+            _ => width,
+        }
     }
 
+    /// 播放时显示的高度，已考虑像素宽高比与旋转
+    pub fn display_height(&self) -> u16 {
+        let (width, height) = self.upright_size();
+        match self.rotation() {
+            90 | 270 => width,
+            _ => height,
+        }
+    }
+
+    /// 显示宽高比（DAR），已考虑像素宽高比与旋转
     pub fn ratio(&self) -> f32 {
-        self.width as f32 / self.height as f32
+        self.display_width() as f32 / self.display_height() as f32
     }
 
+    /// 显示朝向，已考虑像素宽高比与旋转
+    pub fn orientation(&self) -> Orientation {
+        match self.display_width() >= self.display_height() {
+            true => Orientation::Landscape,
+            false => Orientation::Portrait,
+        }
+    }
+
+    /// 平均帧率，缺失时为基础帧率
+    pub fn frame_rate(&self) -> FrameRate {
+        self.frame_rate.0
+    }
+
+    /// 以小数表示的 [`Metadata::frame_rate`]
     pub fn fps(&self) -> f32 {
-        self.fps
+        self.frame_rate.0.as_f32()
+    }
+
+    /// 基础帧率 r_frame_rate，即能准确表示所有时间戳的最低帧率
+    pub fn r_frame_rate(&self) -> Option<FrameRate> {
+        self.video_stream().r_frame_rate.as_deref()?.parse().ok()
+    }
+
+    /// 平均帧率 avg_frame_rate
+    pub fn avg_frame_rate(&self) -> Option<FrameRate> {
+        self.video_stream().avg_frame_rate.as_deref()?.parse().ok()
     }
 
+    /// 是否为可变帧率，例如手机拍摄的视频
+    ///
+    /// 固定帧率的视频两种帧率相同，相差超过 0.1% 时视为可变帧率
+    pub fn is_vfr(&self) -> bool {
+        match (self.r_frame_rate(), self.avg_frame_rate()) {
+            (Some(r), Some(avg)) => (r.as_f32() - avg.as_f32()).abs() > r.as_f32() * 0.001,
+            _ => false,
+        }
+    }
+
+    /// 时长，单位秒，不可用时为 0
     pub fn duration(&self) -> f32 {
-        self.duration
+        self.duration.map_or(0.0, |(duration, _)| duration)
     }
 
+    /// 文件大小，单位字节
     pub fn size(&self) -> u64 {
-        self.size
+        self.size.0
+    }
+
+    /// 时长、帧率与文件大小的来源
+    pub fn sources(&self) -> Sources {
+        Sources {
+            duration: self.duration.map(|(_, source)| source),
+            fps: self.frame_rate.1,
+            size: self.size.1,
+        }
     }
 
+    /// 主视频流的色彩信息
+    pub fn color(&self) -> ColorInfo {
+        self.video_stream().color()
+    }
+
+    /// 主视频流为 HDR 时返回其传输特性
+    pub fn hdr(&self) -> Option<HdrFormat> {
+        self.color().hdr()
+    }
+
+    /// 视频流的总帧数，部分封装格式（例如 mkv）不提供，统计过数据包时使用数据包数量
+    pub fn frames(&self) -> Option<u64> {
+        let stream = self.video_stream();
+        stream.nb_frames.or(stream.nb_read_packets)
+    }
+
+    /// 显示分辨率，已考虑像素宽高比与旋转
+    ///
+    /// ffmpeg 默认会按旋转角度自动旋转画面，滤镜看到的就是显示分辨率
     pub fn resolution(&self) -> Result<Resolution, ResolutionError> {
-        Resolution::new(self.width, self.height)
+        Resolution::new(self.display_width(), self.display_height())
     }
 
+    /// 显示分辨率的像素数
     pub fn pixels(&self) -> u32 {
-        (self.width as u32) * (self.height as u32)
+        (self.display_width() as u32) * (self.display_height() as u32)
+    }
+}
+
+/// 以 JSON 格式调用 ffprobe，返回 stdout
+fn ffprobe(video: &Path, args: &[&str]) -> Result<Vec<u8>, MetadataError> {
+    let output = Command::new(Binaries::current().ffprobe())
+        .args(["-v", "error", "-of", "json"])
+        .args(args)
+        .arg(video)
+        .output()?;
+
+    if !output.status.success() {
+        let error_msg = if output.stderr.is_empty() {
+            format!("Ffprobe exited with status {}", output.status)
+        } else {
+            String::from_utf8_lossy(&output.stderr).into_owned()
+        };
+        return Err(MetadataError::Ffprobe(error_msg));
+    }
+
+    Ok(output.stdout)
+}
+
+/// avg_frame_rate 为 0/0 时（例如部分 ts）回退到 r_frame_rate
+fn resolve_frame_rate(stream: &Stream) -> Option<(FrameRate, Source)> {
+    let parse = |rate: &Option<String>| rate.as_deref()?.parse::<FrameRate>().ok();
+    parse(&stream.avg_frame_rate)
+        .map(|rate| (rate, Source::Stream))
+        .or_else(|| parse(&stream.r_frame_rate).map(|rate| (rate, Source::RFrameRate)))
+}
+
+/// 依次尝试：封装格式、流的时长、流的 DURATION 标签、数据包数量
+///
+/// 流的时长与标签优先取主视频流，其次取所有流中最长的
+fn resolve_duration(probe: &Probe, video: usize, frame_rate: FrameRate) -> Option<(f32, Source)> {
+    let positive = |duration: &f32| *duration > 0.0;
+    let from_streams = |f: fn(&Stream) -> Option<f32>| {
+        f(&probe.streams[video]).filter(positive).or_else(|| {
+            probe
+                .streams
+                .iter()
+                .filter_map(f)
+                .filter(positive)
+                .reduce(f32::max)
+        })
+    };
+
+    if let Some(duration) = probe.format.duration.filter(positive) {
+        return Some((duration, Source::Format));
+    }
+    if let Some(duration) = from_streams(|stream| stream.duration) {
+        return Some((duration, Source::Stream));
+    }
+    if let Some(duration) = from_streams(Stream::tag_duration) {
+        return Some((duration, Source::Tag));
+    }
+    probe.streams[video]
+        .nb_read_packets
+        .filter(|packets| *packets > 0)
+        .map(|packets| (packets as f32 / frame_rate.as_f32(), Source::PacketCount))
+}
+
+/// 封装格式中没有文件大小时读取文件系统
+fn resolve_size(format: &Format) -> Option<(u64, Source)> {
+    format.size.map(|size| (size, Source::Format)).or_else(|| {
+        let metadata = fs::metadata(format.filename.as_deref()?).ok()?;
+        Some((metadata.len(), Source::Filesystem))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn view_over_probe() -> Result<(), MetadataError> {
+        let probe: Probe = serde_json::from_str(include_str!("../ffprobe.json"))?;
+        let metadata = Metadata::from_probe(probe)?;
+
+        assert_eq!((metadata.width(), metadata.height()), (3_840, 2_160));
+        assert!((metadata.fps() - 29.97).abs() < 0.01);
+        assert_eq!(metadata.duration(), 120.5);
+        assert_eq!(metadata.size(), 123_456_789);
+        assert_eq!(metadata.frames(), Some(3_612));
+        assert_eq!(
+            metadata.frame_rate(),
+            FrameRate::new(30_000, 1_001).unwrap()
+        );
+        assert!(!metadata.is_vfr());
+        assert_eq!(metadata.streams_of(CodecType::Audio).count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rotated_phone_video() -> Result<(), MetadataError> {
+        let probe: Probe = serde_json::from_str(
+            r#"{"streams":[{"index":0,"codec_type":"video","width":1920,"height":1080,"avg_frame_rate":"30/1","side_data_list":[{"side_data_type":"Display Matrix","rotation":-90}]}],"format":{"size":"1"}}"#,
+        )?;
+        let metadata = Metadata::from_probe(probe)?;
+
+        assert_eq!((metadata.width(), metadata.height()), (1_920, 1_080));
+        assert_eq!(
+            (metadata.display_width(), metadata.display_height()),
+            (1_080, 1_920)
+        );
+        assert_eq!(metadata.orientation(), Orientation::Portrait);
+        assert_eq!(metadata.resolution(), Ok(Resolution::Vfhd));
+        assert!(metadata.ratio() < 1.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn anamorphic_dvd() -> Result<(), MetadataError> {
+        let probe: Probe = serde_json::from_str(
+            r#"{"streams":[{"index":0,"codec_type":"video","width":720,"height":480,"sample_aspect_ratio":"32:27","display_aspect_ratio":"16:9","avg_frame_rate":"30000/1001"}],"format":{"size":"1"}}"#,
+        )?;
+        let metadata = Metadata::from_probe(probe)?;
+
+        assert_eq!(
+            (metadata.display_width(), metadata.display_height()),
+            (853, 480)
+        );
+        assert!((metadata.ratio() - 16.0 / 9.0).abs() < 0.01);
+
+        Ok(())
+    }
+
+    #[test]
+    fn skip_attached_pic() {
+        let probe: Probe = serde_json::from_str(
+            r#"{"streams":[{"index":0,"codec_type":"video","width":600,"height":600,"avg_frame_rate":"0/0","disposition":{"attached_pic":1}}],"format":{"size":"1"}}"#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            Metadata::from_probe(probe),
+            Err(MetadataError::NoSuchData(_))
+        ));
+    }
+
+    #[test]
+    fn detect_vfr() -> Result<(), MetadataError> {
+        // iPhone 拍摄的视频：基础帧率 30，平均帧率略低
+        let probe: Probe = serde_json::from_str(
+            r#"{"streams":[{"index":0,"codec_type":"video","width":1920,"height":1080,"r_frame_rate":"30/1","avg_frame_rate":"5985000/199799"}],"format":{"size":"1"}}"#,
+        )?;
+        let metadata = Metadata::from_probe(probe)?;
+
+        assert!(metadata.is_vfr());
+        assert_eq!(metadata.r_frame_rate(), FrameRate::new(30, 1).ok());
+        assert!(metadata.frame_rate() < metadata.r_frame_rate().unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    fn prefer_format_values() -> Result<(), MetadataError> {
+        let probe: Probe = serde_json::from_str(include_str!("../ffprobe.json"))?;
+        let sources = Metadata::from_probe(probe)?.sources();
+
+        assert_eq!(sources.duration, Some(Source::Format));
+        assert_eq!(sources.fps, Source::Stream);
+        assert_eq!(sources.size, Source::Format);
+        assert!(!sources.is_fallback());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fallback_for_raw_capture() -> Result<(), MetadataError> {
+        // 裸 ts：没有封装格式时长，avg_frame_rate 为 0/0
+        let probe: Probe = serde_json::from_str(
+            r#"{"streams":[{"index":0,"codec_type":"video","width":1920,"height":1080,"avg_frame_rate":"0/0","r_frame_rate":"25/1","duration":"N/A"},{"index":1,"codec_type":"audio","duration":"60.5"}],"format":{"duration":"N/A","size":"1"}}"#,
+        )?;
+        let metadata = Metadata::from_probe(probe)?;
+
+        assert_eq!(metadata.fps(), 25.0);
+        assert_eq!(metadata.duration(), 60.5);
+        assert_eq!(metadata.sources().fps, Source::RFrameRate);
+        assert_eq!(metadata.sources().duration, Some(Source::Stream));
+        assert!(metadata.sources().is_fallback());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fallback_to_tag_then_packets() -> Result<(), MetadataError> {
+        let probe: Probe = serde_json::from_str(
+            r#"{"streams":[{"index":0,"codec_type":"video","width":1280,"height":720,"avg_frame_rate":"30/1","tags":{"DURATION":"00:00:10.000000000"}}],"format":{"size":"1"}}"#,
+        )?;
+        let metadata = Metadata::from_probe(probe.clone())?;
+        assert_eq!(metadata.duration(), 10.0);
+        assert_eq!(metadata.sources().duration, Some(Source::Tag));
+
+        let mut probe = probe;
+        probe.streams[0].tags.clear();
+        assert_eq!(
+            Metadata::from_probe(probe.clone())?.sources().duration,
+            None
+        );
+
+        probe.streams[0].nb_read_packets = Some(450);
+        let metadata = Metadata::from_probe(probe)?;
+        assert_eq!(metadata.duration(), 15.0);
+        assert_eq!(metadata.frames(), Some(450));
+        assert_eq!(metadata.sources().duration, Some(Source::PacketCount));
+
+        Ok(())
+    }
+
+    #[test]
+    fn size_from_filesystem() -> Result<(), MetadataError> {
+        let path = std::env::temp_dir().join(format!("noobtool-size-{}.ts", std::process::id()));
+        fs::write(&path, [0; 188])?;
+
+        let mut probe: Probe = serde_json::from_str(
+            r#"{"streams":[{"index":0,"codec_type":"video","width":1280,"height":720,"avg_frame_rate":"30/1"}]}"#,
+        )?;
+        probe.format.filename = Some(path.to_string_lossy().into_owned());
+        let metadata = Metadata::from_probe(probe);
+        fs::remove_file(&path)?;
+
+        let metadata = metadata?;
+        assert_eq!(metadata.size(), 188);
+        assert_eq!(metadata.sources().size, Source::Filesystem);
+
+        Ok(())
     }
 }
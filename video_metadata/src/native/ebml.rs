@@ -0,0 +1,486 @@
+//! Matroska/WebM 解析
+//!
+//! https://www.matroska.org/technical/elements.html
+
+use super::{HeaderError, HeaderResult, be_uint, frame_rate, gcd};
+use crate::{CodecType, Format, Probe, Stream};
+use std::io::{Read, Seek, SeekFrom};
+
+const EBML: u32 = 0x1A45DFA3;
+const DOC_TYPE: u32 = 0x4282;
+const SEGMENT: u32 = 0x18538067;
+const CLUSTER: u32 = 0x1F43B675;
+
+const INFO: u32 = 0x1549A966;
+const TIMESTAMP_SCALE: u32 = 0x2AD7B1;
+const DURATION: u32 = 0x4489;
+
+const TRACKS: u32 = 0x1654AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_TYPE: u32 = 0x83;
+const CODEC_ID: u32 = 0x86;
+const DEFAULT_DURATION: u32 = 0x23E383;
+const LANGUAGE: u32 = 0x22B59C;
+const FLAG_DEFAULT: u32 = 0x88;
+const FLAG_FORCED: u32 = 0x55AA;
+
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+const DISPLAY_WIDTH: u32 = 0x54B0;
+const DISPLAY_HEIGHT: u32 = 0x54BA;
+
+const AUDIO: u32 = 0xE1;
+const SAMPLING_FREQUENCY: u32 = 0xB5;
+const CHANNELS: u32 = 0x9F;
+
+/// 头部元素一般只有几 KB，超过该大小视为文件损坏
+const MAX_ELEMENT_SIZE: u64 = 16 * 1024 * 1024;
+
+pub(super) fn read<R: Read + Seek>(reader: &mut R) -> HeaderResult<Probe> {
+    let (id, size) = element_header(reader)?;
+    if id != EBML {
+        return Err(HeaderError::Invalid("EBML header"));
+    }
+    let header = read_payload(reader, size)?;
+    match find(&header, DOC_TYPE)?.map(string).as_deref() {
+        Some("matroska" | "webm") => {}
+        _ => return Err(HeaderError::Unsupported),
+    }
+
+    let (id, segment_size) = element_header(reader)?;
+    if id != SEGMENT {
+        return Err(HeaderError::Invalid("segment"));
+    }
+    let segment_end = segment_size.map(|size| reader.stream_position().map(|pos| pos + size));
+    let segment_end = segment_end.transpose()?;
+
+    let mut info = None;
+    let mut tracks = None;
+    // Info 与 Tracks 都在第一个 Cluster 之前
+    while info.is_none() || tracks.is_none() {
+        if segment_end.is_some_and(|end| reader.stream_position().is_ok_and(|pos| pos >= end)) {
+            break;
+        }
+        let (id, size) = match element_header(reader) {
+            Ok(header) => header,
+            Err(HeaderError::IO(_)) => break,
+            Err(e) => return Err(e),
+        };
+        match id {
+            INFO => info = Some(read_payload(reader, size)?),
+            TRACKS => tracks = Some(read_payload(reader, size)?),
+            CLUSTER => break,
+            _ => {
+                let size = size.ok_or(HeaderError::Invalid("element size"))?;
+                reader.seek(SeekFrom::Current(size as i64))?;
+            }
+        }
+    }
+
+    let tracks = tracks.ok_or(HeaderError::Invalid("missing tracks"))?;
+    let mut streams = Vec::new();
+    for item in elements(&tracks) {
+        let (id, payload) = item?;
+        if id == TRACK_ENTRY
+            && let Some(mut stream) = parse_track(payload)?
+        {
+            stream.index = streams.len() as u32;
+            streams.push(stream);
+        }
+    }
+
+    let format = Format {
+        format_name: Some("matroska,webm".into()),
+        duration: info.as_deref().map(parse_duration).transpose()?.flatten(),
+        ..Format::default()
+    };
+
+    Ok(Probe { streams, format })
+}
+
+/// 可变长度整数，返回 (值, 长度)，`keep_marker` 为真时保留长度标记位（元素 ID）
+fn vint(
+    first: u8,
+    rest: impl FnOnce(usize) -> HeaderResult<Vec<u8>>,
+    keep_marker: bool,
+) -> HeaderResult<(u64, usize)> {
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return Err(HeaderError::Invalid("variable size integer"));
+    }
+    let first = match keep_marker {
+        true => first,
+        false => first & 0xFF_u8.checked_shr(len as u32).unwrap_or_default(),
+    };
+    let value = rest(len - 1)?
+        .iter()
+        .fold(first as u64, |value, byte| (value << 8) | *byte as u64);
+    Ok((value, len))
+}
+
+/// 元素大小的所有数据位都为 1 表示未知大小
+fn is_unknown_size(value: u64, len: usize) -> bool {
+    value == (1 << (7 * len)) - 1
+}
+
+fn element_header<R: Read>(reader: &mut R) -> HeaderResult<(u32, Option<u64>)> {
+    let mut read = |keep_marker| {
+        let mut first = [0];
+        reader.read_exact(&mut first)?;
+        vint(
+            first[0],
+            |len| {
+                let mut rest = vec![0; len];
+                reader.read_exact(&mut rest)?;
+                Ok(rest)
+            },
+            keep_marker,
+        )
+    };
+
+    let (id, _) = read(true)?;
+    let (size, len) = read(false)?;
+    Ok((id as u32, (!is_unknown_size(size, len)).then_some(size)))
+}
+
+fn read_payload<R: Read>(reader: &mut R, size: Option<u64>) -> HeaderResult<Vec<u8>> {
+    match size {
+        Some(size) if size <= MAX_ELEMENT_SIZE => {
+            let mut payload = vec![0; size as usize];
+            reader.read_exact(&mut payload)?;
+            Ok(payload)
+        }
+        _ => Err(HeaderError::Invalid("element size")),
+    }
+}
+
+/// 依次返回 `data` 中的子元素：(ID, 内容)
+fn elements(data: &[u8]) -> impl Iterator<Item = HeaderResult<(u32, &[u8])>> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let result = element(rest);
+        rest = match &result {
+            Ok((_, _, next)) => next,
+            Err(_) => &[],
+        };
+        Some(result.map(|(id, payload, _)| (id, payload)))
+    })
+}
+
+/// 从 `data` 开头解析一个元素，返回 (ID, 内容, 剩余部分)
+fn element(data: &[u8]) -> HeaderResult<(u32, &[u8], &[u8])> {
+    let mut pos = 0;
+    let mut read = |keep_marker| {
+        let first = *data.get(pos).ok_or(HeaderError::Invalid("element"))?;
+        let result = vint(
+            first,
+            |len| {
+                data.get(pos + 1..pos + 1 + len)
+                    .map(<[u8]>::to_vec)
+                    .ok_or(HeaderError::Invalid("element"))
+            },
+            keep_marker,
+        )?;
+        pos += result.1;
+        Ok::<_, HeaderError>(result)
+    };
+
+    let (id, _) = read(true)?;
+    let (size, len) = read(false)?;
+    // 未知大小只会出现在 Segment 与 Cluster，这里按延伸到末尾处理
+    let end = match is_unknown_size(size, len) {
+        true => data.len(),
+        false => pos
+            .checked_add(size as usize)
+            .filter(|end| *end <= data.len())
+            .ok_or(HeaderError::Invalid("element size"))?,
+    };
+    Ok((id as u32, &data[pos..end], &data[end..]))
+}
+
+fn find(data: &[u8], id: u32) -> HeaderResult<Option<&[u8]>> {
+    for item in elements(data) {
+        let (current, payload) = item?;
+        if current == id {
+            return Ok(Some(payload));
+        }
+    }
+    Ok(None)
+}
+
+fn string(payload: &[u8]) -> String {
+    String::from_utf8_lossy(payload)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+fn float(payload: &[u8]) -> HeaderResult<f64> {
+    match payload.len() {
+        0 => Ok(0.0),
+        4 => Ok(f32::from_bits(be_uint(payload) as u32) as f64),
+        8 => Ok(f64::from_bits(be_uint(payload))),
+        _ => Err(HeaderError::Invalid("float")),
+    }
+}
+
+/// Duration 以 TimestampScale（默认 1ms）为单位
+fn parse_duration(info: &[u8]) -> HeaderResult<Option<f32>> {
+    let scale = find(info, TIMESTAMP_SCALE)?.map_or(1_000_000, be_uint);
+    let duration = find(info, DURATION)?.map(float).transpose()?;
+    Ok(duration
+        .filter(|duration| *duration > 0.0)
+        .map(|duration| (duration * scale as f64 / 1e9) as f32))
+}
+
+/// 只保留音视频与字幕轨道
+fn parse_track(entry: &[u8]) -> HeaderResult<Option<Stream>> {
+    let codec_type = match find(entry, TRACK_TYPE)?.map(be_uint) {
+        Some(1) => CodecType::Video,
+        Some(2) => CodecType::Audio,
+        Some(0x11) => CodecType::Subtitle,
+        _ => return Ok(None),
+    };
+
+    let mut stream = Stream {
+        codec_type,
+        codec_name: find(entry, CODEC_ID)?.map(|id| codec_name(&string(id))),
+        ..Stream::default()
+    };
+
+    // 语言缺省时为 eng
+    let language = find(entry, LANGUAGE)?.map_or_else(|| "eng".into(), string);
+    stream.tags.insert("language".into(), language);
+    // FlagDefault 缺省时为 1
+    let default = find(entry, FLAG_DEFAULT)?.map_or(1, be_uint);
+    let forced = find(entry, FLAG_FORCED)?.map_or(0, be_uint);
+    stream.disposition.insert("default".into(), default as u8);
+    stream.disposition.insert("forced".into(), forced as u8);
+
+    if let Some(video) = find(entry, VIDEO)? {
+        let width = find(video, PIXEL_WIDTH)?.map(be_uint);
+        let height = find(video, PIXEL_HEIGHT)?.map(be_uint);
+        stream.width = width.map(|w| w as u16);
+        stream.height = height.map(|h| h as u16);
+
+        let display_width = find(video, DISPLAY_WIDTH)?.map(be_uint);
+        let display_height = find(video, DISPLAY_HEIGHT)?.map(be_uint);
+        if let (Some(w), Some(h), Some(dw), Some(dh)) =
+            (width, height, display_width, display_height)
+            && w > 0
+            && h > 0
+            && dw > 0
+            && dh > 0
+        {
+            let sar = dw.checked_mul(h).zip(dh.checked_mul(w));
+            let (num, den) = sar.ok_or(HeaderError::Invalid("display size"))?;
+            stream.sample_aspect_ratio = Some(ratio(num, den));
+            stream.display_aspect_ratio = Some(ratio(dw, dh));
+        }
+
+        stream.avg_frame_rate = find(entry, DEFAULT_DURATION)?
+            .map(be_uint)
+            .and_then(default_frame_rate);
+        stream.r_frame_rate = stream.avg_frame_rate.clone();
+    }
+
+    if let Some(audio) = find(entry, AUDIO)? {
+        // SamplingFrequency 缺省时为 8000
+        let sample_rate = find(audio, SAMPLING_FREQUENCY)?.map_or(Ok(8_000.0), float)?;
+        stream.sample_rate = Some(sample_rate as u32);
+        // Channels 缺省时为 1
+        stream.channels = Some(find(audio, CHANNELS)?.map_or(1, be_uint) as u16);
+    }
+
+    Ok(Some(stream))
+}
+
+/// DefaultDuration 为每帧的纳秒数，精度有限，接近整数或 NTSC 帧率（N*1000/1001）时取精确值
+fn default_frame_rate(duration: u64) -> Option<String> {
+    if duration == 0 {
+        return None;
+    }
+
+    let fps = 1e9 / duration as f64;
+    let near = |fps: f64| {
+        ((fps - fps.round()).abs() < 0.001 && fps.round() > 0.0).then(|| fps.round() as u64)
+    };
+    match (near(fps), near(fps * 1.001)) {
+        (Some(fps), _) => frame_rate(fps, 1),
+        (_, Some(fps)) => frame_rate(fps * 1000, 1001),
+        _ => frame_rate(1_000_000_000, duration),
+    }
+}
+
+/// 以 ffprobe 的格式表示比例，例如：16:9
+fn ratio(num: u64, den: u64) -> String {
+    let divisor = gcd(num, den);
+    format!("{}:{}", num / divisor, den / divisor)
+}
+
+fn codec_name(id: &str) -> String {
+    match id {
+        "V_MPEG4/ISO/AVC" => "h264".into(),
+        "V_MPEGH/ISO/HEVC" => "hevc".into(),
+        "V_AV1" => "av1".into(),
+        "V_VP9" => "vp9".into(),
+        "V_VP8" => "vp8".into(),
+        "V_MPEG4/ISO/ASP" | "V_MPEG4/ISO/SP" => "mpeg4".into(),
+        "V_MPEG2" => "mpeg2video".into(),
+        "A_AAC" => "aac".into(),
+        "A_OPUS" => "opus".into(),
+        "A_VORBIS" => "vorbis".into(),
+        "A_FLAC" => "flac".into(),
+        "A_AC3" => "ac3".into(),
+        "A_EAC3" => "eac3".into(),
+        "A_DTS" => "dts".into(),
+        "A_MPEG/L3" => "mp3".into(),
+        "S_TEXT/UTF8" => "subrip".into(),
+        "S_TEXT/ASS" | "S_TEXT/SSA" => "ass".into(),
+        "S_TEXT/WEBVTT" => "webvtt".into(),
+        "S_HDMV/PGS" => "hdmv_pgs_subtitle".into(),
+        other => other
+            .split_once('_')
+            .map_or(other, |(_, codec)| codec)
+            .to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    /// 元素大小统一用 8 字节编码
+    fn element(id: u32, payload: &[u8]) -> Vec<u8> {
+        let id = id.to_be_bytes();
+        let skip = id.iter().take_while(|b| **b == 0).count();
+        let mut data = id[skip..].to_vec();
+        data.push(0x01);
+        data.extend_from_slice(&(payload.len() as u64).to_be_bytes()[1..]);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn uint(id: u32, value: u64) -> Vec<u8> {
+        element(id, &value.to_be_bytes())
+    }
+
+    fn fixture(doc_type: &str) -> Vec<u8> {
+        let header = element(EBML, &element(DOC_TYPE, doc_type.as_bytes()));
+
+        let info = element(
+            INFO,
+            &[
+                uint(TIMESTAMP_SCALE, 1_000_000),
+                element(DURATION, &120_500f64.to_be_bytes()),
+            ]
+            .concat(),
+        );
+
+        let video = element(
+            TRACK_ENTRY,
+            &[
+                uint(TRACK_TYPE, 1),
+                element(CODEC_ID, b"V_MPEGH/ISO/HEVC"),
+                uint(DEFAULT_DURATION, 41_708_333),
+                element(
+                    VIDEO,
+                    &[
+                        uint(PIXEL_WIDTH, 1440),
+                        uint(PIXEL_HEIGHT, 1080),
+                        uint(DISPLAY_WIDTH, 1920),
+                        uint(DISPLAY_HEIGHT, 1080),
+                    ]
+                    .concat(),
+                ),
+            ]
+            .concat(),
+        );
+        let audio = element(
+            TRACK_ENTRY,
+            &[
+                uint(TRACK_TYPE, 2),
+                element(CODEC_ID, b"A_OPUS"),
+                element(LANGUAGE, b"jpn"),
+                element(
+                    AUDIO,
+                    &[
+                        element(SAMPLING_FREQUENCY, &48_000f32.to_be_bytes()),
+                        uint(CHANNELS, 6),
+                    ]
+                    .concat(),
+                ),
+            ]
+            .concat(),
+        );
+        let tracks = element(TRACKS, &[video, audio].concat());
+        let cluster = element(CLUSTER, &[0; 64]);
+
+        // Segment 使用未知大小，与直播录制的文件一致
+        let mut segment = vec![0x18, 0x53, 0x80, 0x67, 0xFF];
+        segment.extend([element(0x114D9B74, &[0; 16]), info, tracks, cluster].concat());
+
+        [header, segment].concat()
+    }
+
+    #[test]
+    fn parse_matroska() -> HeaderResult<()> {
+        let probe = read(&mut Cursor::new(fixture("matroska")))?;
+
+        assert_eq!(probe.format.duration, Some(120.5));
+        assert_eq!(probe.streams.len(), 2);
+
+        let video = &probe.streams[0];
+        assert_eq!(video.codec_name.as_deref(), Some("hevc"));
+        assert_eq!((video.width, video.height), (Some(1440), Some(1080)));
+        assert_eq!(video.sample_aspect_ratio.as_deref(), Some("4:3"));
+        assert_eq!(video.display_aspect_ratio.as_deref(), Some("16:9"));
+        assert_eq!(video.avg_frame_rate.as_deref(), Some("24000/1001"));
+        assert_eq!(video.language(), Some("eng"));
+
+        let audio = &probe.streams[1];
+        assert_eq!(audio.codec_name.as_deref(), Some("opus"));
+        assert_eq!(audio.sample_rate, Some(48_000));
+        assert_eq!(audio.channels, Some(6));
+        assert_eq!(audio.language(), Some("jpn"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn snap_default_duration() {
+        assert_eq!(default_frame_rate(40_000_000).as_deref(), Some("25/1"));
+        assert_eq!(
+            default_frame_rate(16_683_333).as_deref(),
+            Some("60000/1001")
+        );
+        assert_eq!(
+            default_frame_rate(33_366_666).as_deref(),
+            Some("30000/1001")
+        );
+        assert_eq!(default_frame_rate(0), None);
+    }
+
+    #[test]
+    fn reject_other_doc_type() {
+        assert!(matches!(
+            read(&mut Cursor::new(fixture("dvb"))),
+            Err(HeaderError::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn variable_size_integer() -> HeaderResult<()> {
+        let none = |_| Ok(vec![]);
+        assert_eq!(vint(0x81, none, false)?, (1, 1));
+        assert_eq!(vint(0x81, none, true)?, (0x81, 1));
+        assert_eq!(vint(0x40, |_| Ok(vec![0x02]), false)?, (2, 2));
+        assert!(is_unknown_size(0x7F, 1));
+        assert!(vint(0x00, none, false).is_err());
+        Ok(())
+    }
+}
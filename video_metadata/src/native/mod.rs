@@ -0,0 +1,128 @@
+//! 不依赖 ffprobe 的容器头解析，只读取 MP4/MOV 的 moov 与 Matroska/WebM 的 Segment 头部
+//!
+//! 只提取宽高、时长、帧率与编码等基本信息，色彩、HDR 等信息仍需 ffprobe
+
+mod ebml;
+mod mp4;
+
+use crate::Probe;
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HeaderError {
+    #[error(transparent)]
+    IO(#[from] io::Error),
+    #[error("unsupported container")]
+    Unsupported,
+    #[error("invalid {0}")]
+    Invalid(&'static str),
+}
+
+pub(crate) type HeaderResult<T> = Result<T, HeaderError>;
+
+#[cfg(test)]
+pub(crate) use mp4::test::fixture as mp4_fixture;
+
+/// 读取文件头，按文件开头的特征选择解析方式
+pub(crate) fn read(path: &Path) -> HeaderResult<Probe> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+
+    let mut probe = read_from(&mut BufReader::new(file))?;
+    probe.format.filename = Some(path.to_string_lossy().into_owned());
+    probe.format.size = Some(size);
+    probe.format.nb_streams = Some(probe.streams.len() as u32);
+    Ok(probe)
+}
+
+fn read_from<R: Read + Seek>(reader: &mut R) -> HeaderResult<Probe> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    match magic {
+        [0x1A, 0x45, 0xDF, 0xA3, ..] => ebml::read(reader),
+        [_, _, _, _, b'f', b't', b'y', b'p']
+        | [_, _, _, _, b'm', b'o', b'o', b'v']
+        | [_, _, _, _, b'f', b'r', b'e', b'e']
+        | [_, _, _, _, b's', b'k', b'i', b'p']
+        | [_, _, _, _, b'w', b'i', b'd', b'e']
+        | [_, _, _, _, b'm', b'd', b'a', b't'] => mp4::read(reader),
+        _ => Err(HeaderError::Unsupported),
+    }
+}
+
+/// 以 ffprobe 的格式表示帧率，例如：30000/1001
+///
+/// 分子分母超出 u32 时按千分之一精度近似
+fn frame_rate(num: u64, den: u64) -> Option<String> {
+    if num == 0 || den == 0 {
+        return None;
+    }
+
+    let divisor = gcd(num, den);
+    let (num, den) = (num / divisor, den / divisor);
+    if num <= u32::MAX as u64 && den <= u32::MAX as u64 {
+        return Some(format!("{}/{}", num, den));
+    }
+
+    let millis = (num as f64 / den as f64 * 1000.0).round() as u64;
+    frame_rate(millis, 1000)
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    match b {
+        0 => a,
+        b => gcd(b, a % b),
+    }
+}
+
+/// 大端无符号整数，最长 8 字节
+fn be_uint(data: &[u8]) -> u64 {
+    data.iter()
+        .fold(0, |value, byte| (value << 8) | *byte as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reduce_frame_rate() {
+        assert_eq!(
+            frame_rate(3_612 * 30_000, 3_612 * 1_001).as_deref(),
+            Some("30000/1001")
+        );
+        assert_eq!(frame_rate(48_000, 2_000).as_deref(), Some("24/1"));
+        assert_eq!(frame_rate(0, 1), None);
+    }
+
+    #[test]
+    fn read_file() -> HeaderResult<()> {
+        let path = std::env::temp_dir().join(format!("noobtool-native-{}.mp4", std::process::id()));
+        let data = mp4_fixture([1, 0]);
+        std::fs::write(&path, &data)?;
+
+        let probe = read(&path);
+        std::fs::remove_file(&path)?;
+        let metadata = crate::Metadata::from_probe(probe?).unwrap();
+
+        assert_eq!((metadata.width(), metadata.height()), (1920, 1080));
+        assert_eq!(metadata.size(), data.len() as u64);
+        assert_eq!(metadata.format().nb_streams, Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn reject_unknown_container() {
+        let mut reader = io::Cursor::new(b"RIFF\0\0\0\0AVI LIST".to_vec());
+        assert!(matches!(
+            read_from(&mut reader),
+            Err(HeaderError::Unsupported)
+        ));
+    }
+}
@@ -0,0 +1,398 @@
+//! ISO BMFF（MP4/MOV）解析
+//!
+//! https://developer.apple.com/documentation/quicktime-file-format
+
+use super::{HeaderError, HeaderResult, be_uint, frame_rate};
+use crate::{CodecType, Format, Probe, SideData, Stream};
+use std::io::{Read, Seek, SeekFrom};
+
+/// moov 一般只有几百 KB，超过该大小视为文件损坏
+const MAX_MOOV_SIZE: u64 = 64 * 1024 * 1024;
+
+pub(super) fn read<R: Read + Seek>(reader: &mut R) -> HeaderResult<Probe> {
+    let moov = find_moov(reader)?;
+    parse_moov(&moov)
+}
+
+/// 跳过 mdat 等顶层 box，只把 moov 读入内存
+fn find_moov<R: Read + Seek>(reader: &mut R) -> HeaderResult<Vec<u8>> {
+    let end = reader.seek(SeekFrom::End(0))?;
+    let mut pos = reader.seek(SeekFrom::Start(0))?;
+
+    while pos + 8 <= end {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        let (size, header_len) = match be_uint(&header[..4]) {
+            // 延伸到文件末尾
+            0 => (end - pos, 8),
+            // 64 位大小
+            1 => {
+                let mut large = [0; 8];
+                reader.read_exact(&mut large)?;
+                (be_uint(&large), 16)
+            }
+            size => (size, 8),
+        };
+        // 64 位大小可能接近 u64::MAX，相加时需要检查溢出
+        let box_end = pos
+            .checked_add(size)
+            .filter(|&box_end| size >= header_len && box_end <= end)
+            .ok_or(HeaderError::Invalid("box size"))?;
+
+        if &header[4..] == b"moov" {
+            if size > MAX_MOOV_SIZE {
+                return Err(HeaderError::Invalid("moov size"));
+            }
+            let mut moov = vec![0; (size - header_len) as usize];
+            reader.read_exact(&mut moov)?;
+            return Ok(moov);
+        }
+
+        pos = reader.seek(SeekFrom::Start(box_end))?;
+    }
+
+    Err(HeaderError::Invalid("missing moov"))
+}
+
+/// 依次返回 `data` 中的子 box：(类型, 内容)
+fn boxes(data: &[u8]) -> impl Iterator<Item = HeaderResult<(&[u8], &[u8])>> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if rest.len() < 8 {
+            return None;
+        }
+        let (size, header_len) = match be_uint(&rest[..4]) {
+            0 => (rest.len() as u64, 8),
+            1 if rest.len() >= 16 => (be_uint(&rest[8..16]), 16),
+            1 => return Some(Err(HeaderError::Invalid("box size"))),
+            size => (size, 8),
+        };
+        if size < header_len || size > rest.len() as u64 {
+            rest = &[];
+            return Some(Err(HeaderError::Invalid("box size")));
+        }
+
+        let (current, next) = rest.split_at(size as usize);
+        rest = next;
+        Some(Ok((&current[4..8], &current[header_len as usize..])))
+    })
+}
+
+/// 查找第一个指定类型的子 box
+fn child<'a>(data: &'a [u8], kind: &[u8; 4]) -> HeaderResult<Option<&'a [u8]>> {
+    for item in boxes(data) {
+        let (current, payload) = item?;
+        if current == kind {
+            return Ok(Some(payload));
+        }
+    }
+    Ok(None)
+}
+
+/// 按路径查找，例如 mdia/minf/stbl
+fn path<'a>(data: &'a [u8], kinds: &[&[u8; 4]]) -> HeaderResult<Option<&'a [u8]>> {
+    kinds.iter().try_fold(Some(data), |data, kind| match data {
+        Some(data) => child(data, kind),
+        None => Ok(None),
+    })
+}
+
+fn slice(data: &[u8], start: usize, len: usize) -> HeaderResult<&[u8]> {
+    data.get(start..start + len)
+        .ok_or(HeaderError::Invalid("box too short"))
+}
+
+/// mvhd 与 mdhd 的时间单位与时长，位置取决于版本
+fn timescale_duration(payload: &[u8]) -> HeaderResult<(u64, u64)> {
+    match payload.first() {
+        Some(1) => Ok((
+            be_uint(slice(payload, 20, 4)?),
+            be_uint(slice(payload, 24, 8)?),
+        )),
+        Some(_) => Ok((
+            be_uint(slice(payload, 12, 4)?),
+            be_uint(slice(payload, 16, 4)?),
+        )),
+        None => Err(HeaderError::Invalid("empty header box")),
+    }
+}
+
+fn seconds((timescale, duration): (u64, u64)) -> Option<f32> {
+    (timescale > 0 && duration > 0).then(|| (duration as f64 / timescale as f64) as f32)
+}
+
+fn parse_moov(moov: &[u8]) -> HeaderResult<Probe> {
+    let mvhd = child(moov, b"mvhd")?.ok_or(HeaderError::Invalid("missing mvhd"))?;
+    let mut format = Format {
+        format_name: Some("mov,mp4,m4a,3gp,3g2,mj2".into()),
+        duration: seconds(timescale_duration(mvhd)?),
+        ..Format::default()
+    };
+
+    let mut streams = Vec::new();
+    for item in boxes(moov) {
+        let (kind, payload) = item?;
+        if kind == b"trak"
+            && let Some(mut stream) = parse_trak(payload)?
+        {
+            stream.index = streams.len() as u32;
+            streams.push(stream);
+        }
+    }
+
+    if format.duration.is_none() {
+        format.duration = streams
+            .iter()
+            .filter_map(|stream| stream.duration)
+            .reduce(f32::max);
+    }
+
+    Ok(Probe { streams, format })
+}
+
+/// 只保留音视频轨道，其他轨道（例如 tmcd）返回 `None`
+fn parse_trak(trak: &[u8]) -> HeaderResult<Option<Stream>> {
+    let mdia = child(trak, b"mdia")?.ok_or(HeaderError::Invalid("missing mdia"))?;
+    let hdlr = child(mdia, b"hdlr")?.ok_or(HeaderError::Invalid("missing hdlr"))?;
+    let codec_type = match slice(hdlr, 8, 4)? {
+        b"vide" => CodecType::Video,
+        b"soun" => CodecType::Audio,
+        _ => return Ok(None),
+    };
+
+    let mdhd = child(mdia, b"mdhd")?.ok_or(HeaderError::Invalid("missing mdhd"))?;
+    let (timescale, duration) = timescale_duration(mdhd)?;
+    let stbl = path(mdia, &[b"minf", b"stbl"])?.ok_or(HeaderError::Invalid("missing stbl"))?;
+
+    let mut stream = Stream {
+        codec_type,
+        duration: seconds((timescale, duration)),
+        ..Stream::default()
+    };
+
+    // stsd：版本与标志 4 字节，条目数 4 字节，之后是第一个 sample entry
+    let stsd = child(stbl, b"stsd")?.ok_or(HeaderError::Invalid("missing stsd"))?;
+    let entry = match boxes(stsd.get(8..).unwrap_or_default()).next() {
+        Some(entry) => Some(entry?),
+        None => None,
+    };
+
+    if let Some((fourcc, entry)) = entry {
+        stream.codec_name = Some(codec_name(fourcc));
+        match codec_type {
+            // VisualSampleEntry：reserved 6 + data_reference_index 2 + pre_defined/reserved 16，之后是宽高
+            CodecType::Video => {
+                stream.width = Some(be_uint(slice(entry, 24, 2)?) as u16);
+                stream.height = Some(be_uint(slice(entry, 26, 2)?) as u16);
+            }
+            // AudioSampleEntry：reserved 6 + data_reference_index 2 + reserved 8，之后是声道数
+            _ => {
+                stream.channels = Some(be_uint(slice(entry, 16, 2)?) as u16);
+                stream.sample_rate = Some((be_uint(slice(entry, 24, 4)?) >> 16) as u32);
+            }
+        }
+    }
+
+    if codec_type == CodecType::Video {
+        // stts：每项是 (sample_count, sample_delta)，样本总数即帧数
+        if let Some(stts) = child(stbl, b"stts")? {
+            let count = be_uint(slice(stts, 4, 4)?) as usize;
+            let frames = (0..count).try_fold(0_u64, |frames, i| {
+                frames
+                    .checked_add(be_uint(slice(stts, 8 + i * 8, 4)?))
+                    .ok_or(HeaderError::Invalid("sample count"))
+            })?;
+            let rate = frames
+                .checked_mul(timescale)
+                .ok_or(HeaderError::Invalid("sample count"))?;
+            stream.nb_frames = (frames > 0).then_some(frames);
+            stream.avg_frame_rate = frame_rate(rate, duration);
+            stream.r_frame_rate = stream.avg_frame_rate.clone();
+        }
+
+        if let Some(tkhd) = child(trak, b"tkhd")? {
+            stream.side_data_list = display_matrix(tkhd)?.into_iter().collect();
+        }
+    }
+
+    Ok(Some(stream))
+}
+
+/// tkhd 中的显示矩阵，没有旋转时返回 `None`
+fn display_matrix(tkhd: &[u8]) -> HeaderResult<Option<SideData>> {
+    // 版本 0 的时间字段共 20 字节，版本 1 为 32 字节，之后是 reserved 8 + layer 2 + alternate_group 2 + volume 2 + reserved 2
+    let matrix = match tkhd.first() {
+        Some(1) => 4 + 32 + 16,
+        _ => 4 + 20 + 16,
+    };
+    // 矩阵前两项 a、b 为 16.16 定点数
+    let a = be_uint(slice(tkhd, matrix, 4)?) as u32 as i32 as f64;
+    let b = be_uint(slice(tkhd, matrix + 4, 4)?) as u32 as i32 as f64;
+
+    // 与 ffmpeg 的 av_display_rotation_get 一致：逆时针为正
+    let rotation = -b.atan2(a).to_degrees();
+    Ok((rotation.round() != 0.0).then(|| SideData {
+        side_data_type: Some("Display Matrix".into()),
+        rotation: Some(rotation as f32),
+    }))
+}
+
+fn codec_name(fourcc: &[u8]) -> String {
+    match fourcc {
+        b"avc1" | b"avc3" => "h264".into(),
+        b"hvc1" | b"hev1" => "hevc".into(),
+        b"av01" => "av1".into(),
+        b"vp09" => "vp9".into(),
+        b"mp4v" => "mpeg4".into(),
+        b"mp4a" => "aac".into(),
+        b"Opus" => "opus".into(),
+        b"ac-3" => "ac3".into(),
+        b"ec-3" => "eac3".into(),
+        other => String::from_utf8_lossy(other).trim().to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn full_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        mp4_box(kind, &[&[0, 0, 0, 0], payload].concat())
+    }
+
+    fn u32s(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+
+    /// 版本 0 的 mvhd/mdhd：创建时间、修改时间、时间单位、时长
+    fn header(kind: &[u8; 4], timescale: u32, duration: u32) -> Vec<u8> {
+        full_box(kind, &u32s(&[0, 0, timescale, duration]))
+    }
+
+    fn tkhd(matrix: [i32; 2], width: u16, height: u16) -> Vec<u8> {
+        let mut payload = vec![0; 20 + 16];
+        payload.extend(matrix.iter().flat_map(|v| (v << 16).to_be_bytes()));
+        payload.extend(vec![0; 28]);
+        payload.extend(u32s(&[(width as u32) << 16, (height as u32) << 16]));
+        full_box(b"tkhd", &payload)
+    }
+
+    fn video_trak(rotation_matrix: [i32; 2]) -> Vec<u8> {
+        let mut avc1 = vec![0; 24];
+        avc1.extend(1920u16.to_be_bytes());
+        avc1.extend(1080u16.to_be_bytes());
+        avc1.extend(vec![0; 50]);
+
+        let stsd = full_box(b"stsd", &[u32s(&[1]), mp4_box(b"avc1", &avc1)].concat());
+        // 3000 帧，每帧 1001，时间单位 30000：29.97fps，100.1 秒
+        let stts = full_box(b"stts", &u32s(&[1, 3_000, 1_001]));
+        let stbl = mp4_box(b"stbl", &[stsd, stts].concat());
+        let minf = mp4_box(b"minf", &stbl);
+        let hdlr = full_box(
+            b"hdlr",
+            &[u32s(&[0]), b"vide".to_vec(), vec![0; 13]].concat(),
+        );
+        let mdia = mp4_box(
+            b"mdia",
+            &[header(b"mdhd", 30_000, 3_003_000), hdlr, minf].concat(),
+        );
+        mp4_box(b"trak", &[tkhd(rotation_matrix, 1920, 1080), mdia].concat())
+    }
+
+    fn audio_trak() -> Vec<u8> {
+        let mut mp4a = vec![0; 16];
+        mp4a.extend(2u16.to_be_bytes());
+        mp4a.extend(vec![0; 6]);
+        mp4a.extend((48_000u32 << 16).to_be_bytes());
+
+        let stsd = full_box(b"stsd", &[u32s(&[1]), mp4_box(b"mp4a", &mp4a)].concat());
+        let stbl = mp4_box(b"stbl", &stsd);
+        let minf = mp4_box(b"minf", &stbl);
+        let hdlr = full_box(
+            b"hdlr",
+            &[u32s(&[0]), b"soun".to_vec(), vec![0; 13]].concat(),
+        );
+        let mdia = mp4_box(
+            b"mdia",
+            &[header(b"mdhd", 48_000, 4_800_000), hdlr, minf].concat(),
+        );
+        mp4_box(b"trak", &[tkhd([1, 0], 0, 0), mdia].concat())
+    }
+
+    /// mdat 在 moov 之前的普通 MP4
+    pub(crate) fn fixture(rotation_matrix: [i32; 2]) -> Vec<u8> {
+        let ftyp = mp4_box(b"ftyp", b"isom\0\0\x02\0isomavc1");
+        let mdat = mp4_box(b"mdat", &[0; 1024]);
+        let moov = mp4_box(
+            b"moov",
+            &[
+                header(b"mvhd", 1_000, 100_100),
+                video_trak(rotation_matrix),
+                audio_trak(),
+            ]
+            .concat(),
+        );
+        [ftyp, mdat, moov].concat()
+    }
+
+    #[test]
+    fn parse_mp4() -> HeaderResult<()> {
+        let probe = read(&mut Cursor::new(fixture([1, 0])))?;
+
+        assert_eq!(probe.format.duration, Some(100.1));
+        assert_eq!(probe.streams.len(), 2);
+
+        let video = &probe.streams[0];
+        assert_eq!(video.codec_type, CodecType::Video);
+        assert_eq!(video.codec_name.as_deref(), Some("h264"));
+        assert_eq!((video.width, video.height), (Some(1920), Some(1080)));
+        assert_eq!(video.avg_frame_rate.as_deref(), Some("30000/1001"));
+        assert_eq!(video.nb_frames, Some(3_000));
+        assert_eq!(video.rotation(), 0);
+
+        let audio = &probe.streams[1];
+        assert_eq!(audio.codec_name.as_deref(), Some("aac"));
+        assert_eq!(audio.channels, Some(2));
+        assert_eq!(audio.sample_rate, Some(48_000));
+        assert_eq!(audio.index, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_rotation_matrix() -> HeaderResult<()> {
+        // 手机竖拍：a=0, b=1
+        let probe = read(&mut Cursor::new(fixture([0, 1])))?;
+        assert_eq!(probe.streams[0].rotation(), 90);
+        Ok(())
+    }
+
+    #[test]
+    fn reject_truncated_file() {
+        let mut data = fixture([1, 0]);
+        data.truncate(data.len() - 100);
+        assert!(read(&mut Cursor::new(data)).is_err());
+    }
+
+    #[test]
+    fn reject_overflowing_large_size() {
+        // size 为 1 时使用 64 位大小，u64::MAX 加上位置会溢出
+        let mut data = fixture([1, 0]);
+        let mut large = 1_u32.to_be_bytes().to_vec();
+        large.extend_from_slice(b"free");
+        large.extend_from_slice(&u64::MAX.to_be_bytes());
+        data.splice(0..0, large);
+        assert!(matches!(
+            read(&mut Cursor::new(data)),
+            Err(HeaderError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,348 @@
+use crate::ColorInfo;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+/// `ffprobe -of json -show_streams -show_format` 的输出
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Probe {
+    #[serde(default)]
+    pub streams: Vec<Stream>,
+    #[serde(default)]
+    pub format: Format,
+}
+
+/// 流的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CodecType {
+    Video,
+    Audio,
+    Subtitle,
+    Attachment,
+    Data,
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+/// 单个流的信息，ffprobe 缺省的字段为 `None`
+///
+/// ffprobe 会把部分数值输出为字符串（例如 bit_rate），这里统一解析为数值，无法解析时视为缺省
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stream {
+    pub index: u32,
+    pub codec_type: CodecType,
+    pub codec_name: Option<String>,
+    pub profile: Option<String>,
+    pub width: Option<u16>,
+    pub height: Option<u16>,
+    /// 像素宽高比，例如：8:9，未知时为 0:1 或 N/A
+    pub sample_aspect_ratio: Option<String>,
+    /// 显示宽高比，例如：4:3
+    pub display_aspect_ratio: Option<String>,
+    pub pix_fmt: Option<String>,
+    pub color_range: Option<String>,
+    pub color_space: Option<String>,
+    pub color_transfer: Option<String>,
+    pub color_primaries: Option<String>,
+    #[serde(deserialize_with = "number")]
+    pub bits_per_raw_sample: Option<u8>,
+    /// 平均帧率，例如：30000/1001
+    pub avg_frame_rate: Option<String>,
+    /// 基础帧率，例如：30000/1001
+    pub r_frame_rate: Option<String>,
+    #[serde(deserialize_with = "number")]
+    pub bit_rate: Option<u64>,
+    #[serde(deserialize_with = "number")]
+    pub nb_frames: Option<u64>,
+    /// `-count_packets` 统计的数据包数量，只在缺少时长时获取
+    #[serde(deserialize_with = "number")]
+    pub nb_read_packets: Option<u64>,
+    #[serde(deserialize_with = "number")]
+    pub duration: Option<f32>,
+    pub channels: Option<u16>,
+    pub channel_layout: Option<String>,
+    #[serde(deserialize_with = "number")]
+    pub sample_rate: Option<u32>,
+    /// 例如：default、forced、attached_pic，取值 0 或 1
+    pub disposition: BTreeMap<String, u8>,
+    pub tags: BTreeMap<String, String>,
+    /// 例如：Display Matrix
+    pub side_data_list: Vec<SideData>,
+}
+
+/// 流的附加数据，目前只关心显示矩阵中的旋转角度
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SideData {
+    pub side_data_type: Option<String>,
+    /// 显示矩阵的旋转角度，逆时针为正，例如手机竖拍的视频为 -90
+    #[serde(deserialize_with = "number")]
+    pub rotation: Option<f32>,
+}
+
+/// 封装格式的信息
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Format {
+    pub filename: Option<String>,
+    pub nb_streams: Option<u32>,
+    /// 例如：mov,mp4,m4a,3gp,3g2,mj2
+    pub format_name: Option<String>,
+    pub format_long_name: Option<String>,
+    #[serde(deserialize_with = "number")]
+    pub duration: Option<f32>,
+    #[serde(deserialize_with = "number")]
+    pub size: Option<u64>,
+    #[serde(deserialize_with = "number")]
+    pub bit_rate: Option<u64>,
+    pub tags: BTreeMap<String, String>,
+}
+
+impl Stream {
+    pub fn is_video(&self) -> bool {
+        self.codec_type == CodecType::Video
+    }
+
+    /// 封面图在 ffprobe 中也是视频流
+    pub fn is_attached_pic(&self) -> bool {
+        self.has_disposition("attached_pic")
+    }
+
+    pub fn has_disposition(&self, name: &str) -> bool {
+        self.disposition.get(name).is_some_and(|v| *v != 0)
+    }
+
+    /// 语言标签，例如：eng
+    pub fn language(&self) -> Option<&str> {
+        self.tags.get("language").map(String::as_str)
+    }
+
+    /// 色彩信息，ffprobe 输出 unknown 的字段视为缺省
+    pub fn color(&self) -> ColorInfo {
+        let known = |value: &Option<String>| value.clone().filter(|v| v != "unknown");
+        ColorInfo {
+            primaries: known(&self.color_primaries),
+            transfer: known(&self.color_transfer),
+            space: known(&self.color_space),
+            range: known(&self.color_range),
+        }
+    }
+
+    /// 像素宽高比，未知或无效时为 `None`
+    pub fn sar(&self) -> Option<f32> {
+        parse_ratio(self.sample_aspect_ratio.as_deref()?)
+    }
+
+    /// 显示宽高比，未知或无效时为 `None`
+    pub fn dar(&self) -> Option<f32> {
+        parse_ratio(self.display_aspect_ratio.as_deref()?)
+    }
+
+    /// 播放时需要顺时针旋转的角度，取值 0、90、180、270
+    ///
+    /// 优先使用显示矩阵，旧版本 ffmpeg 写入的 rotate 标签作为后备
+    pub fn rotation(&self) -> u16 {
+        let clockwise = self
+            .side_data_list
+            .iter()
+            .find_map(|side_data| side_data.rotation)
+            .map(|rotation| -rotation)
+            .or_else(|| self.tags.get("rotate")?.trim().parse().ok())
+            .unwrap_or_default();
+
+        // 只处理 90 度的整数倍，其他角度按最接近的处理
+        ((clockwise / 90.0).round() as i32 * 90).rem_euclid(360) as u16
+    }
+
+    /// DURATION 标签中的时长，单位秒，例如：00:02:00.500000000
+    ///
+    /// mkvmerge 等工具写入的标签名可能带语言后缀，例如：DURATION-eng
+    pub fn tag_duration(&self) -> Option<f32> {
+        let (_, value) = self
+            .tags
+            .iter()
+            .find(|(key, _)| key.as_str() == "DURATION" || key.starts_with("DURATION-"))?;
+        let mut parts = value.trim().splitn(3, ':');
+        let hours: u32 = parts.next()?.parse().ok()?;
+        let minutes: u32 = parts.next()?.parse().ok()?;
+        let seconds: f32 = parts.next()?.parse().ok()?;
+        let duration = (hours * 3_600 + minutes * 60) as f32 + seconds;
+        (duration > 0.0).then_some(duration)
+    }
+
+    /// 视频的位深，ffprobe 未给出 bits_per_raw_sample 时根据 pix_fmt 推断
+    pub fn bit_depth(&self) -> Option<u8> {
+        self.bits_per_raw_sample
+            .or_else(|| self.pix_fmt.as_deref().map(pix_fmt_depth))
+    }
+}
+
+/// 按 ffmpeg 的像素格式命名规则取位深，数字只在特定位置表示位深
+///
+/// - `yuv420p10le`、`gbrap12be`：最后一个 `p` 之后的数字
+/// - `gray10le`：`gray` 之后的数字
+/// - `p010le`、`y210le`：半平面与打包格式，后两位数字
+/// - `rgb48le`、`rgba64le`：每个分量 16 位
+/// - 其他格式（`nv12`、`yuv410p`、`rgb24` 等）为 8 位
+fn pix_fmt_depth(pix_fmt: &str) -> u8 {
+    let name = pix_fmt
+        .strip_suffix("le")
+        .or_else(|| pix_fmt.strip_suffix("be"))
+        .unwrap_or(pix_fmt);
+    let packed =
+        name.len() == 4 && name.starts_with(['p', 'y']) && name[1..].parse::<u16>().is_ok();
+
+    let depth = match name {
+        "rgb48" | "bgr48" | "rgba64" | "bgra64" => Some("16"),
+        _ if packed => Some(&name[2..]),
+        _ => name
+            .strip_prefix("gray")
+            .or_else(|| name.rsplit_once('p').map(|(_, depth)| depth)),
+    };
+    depth.and_then(|depth| depth.parse().ok()).unwrap_or(8)
+}
+
+/// 解析 `a:b` 形式的比例，任何一边为 0 都视为无效
+fn parse_ratio(s: &str) -> Option<f32> {
+    let (num, den) = s.split_once(':')?;
+    let num: u32 = num.parse().ok()?;
+    let den: u32 = den.parse().ok()?;
+    (num > 0 && den > 0).then(|| num as f32 / den as f32)
+}
+
+/// 接受数字或数字字符串，其他值（例如 "N/A"）视为缺省
+fn number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Str(String),
+        Num(serde_json::Number),
+    }
+
+    Ok(match Option::<Raw>::deserialize(deserializer)? {
+        Some(Raw::Str(s)) => s.trim().parse().ok(),
+        Some(Raw::Num(n)) => n.to_string().parse().ok(),
+        None => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::HdrFormat;
+
+    #[test]
+    fn parse_full_inventory() {
+        let probe: Probe = serde_json::from_str(include_str!("../ffprobe.json")).unwrap();
+
+        assert_eq!(probe.streams.len(), 5);
+        let video = &probe.streams[0];
+        assert!(video.is_video());
+        assert_eq!(video.codec_name.as_deref(), Some("hevc"));
+        assert_eq!(video.profile.as_deref(), Some("Main 10"));
+        assert_eq!(video.bit_depth(), Some(10));
+        assert_eq!(video.sar(), Some(1.0));
+        assert_eq!(video.color().hdr(), Some(HdrFormat::Pq));
+        assert_eq!(video.color().space.as_deref(), Some("bt2020nc"));
+        assert_eq!(video.dar(), Some(16.0 / 9.0));
+        assert_eq!(video.bit_rate, Some(8_000_000));
+        assert_eq!(video.nb_frames, Some(3_612));
+        assert!(video.has_disposition("default"));
+
+        let audio = &probe.streams[1];
+        assert_eq!(audio.codec_type, CodecType::Audio);
+        assert_eq!(audio.channels, Some(6));
+        assert_eq!(audio.sample_rate, Some(48_000));
+        assert_eq!(audio.language(), Some("jpn"));
+
+        assert_eq!(probe.streams[2].codec_type, CodecType::Subtitle);
+        assert!(probe.streams[2].has_disposition("forced"));
+        assert_eq!(probe.streams[3].codec_type, CodecType::Attachment);
+        assert!(probe.streams[4].is_attached_pic());
+
+        assert_eq!(probe.format.duration, Some(120.5));
+        assert_eq!(probe.format.size, Some(123_456_789));
+        assert_eq!(
+            probe.format.tags.get("title").map(String::as_str),
+            Some("demo")
+        );
+    }
+
+    #[test]
+    fn bit_depth_from_pix_fmt() {
+        let depth = |pix_fmt: &str| {
+            Stream {
+                pix_fmt: Some(pix_fmt.into()),
+                ..Stream::default()
+            }
+            .bit_depth()
+        };
+
+        assert_eq!(depth("yuv420p"), Some(8));
+        assert_eq!(depth("yuv410p"), Some(8));
+        assert_eq!(depth("nv12"), Some(8));
+        assert_eq!(depth("nv16"), Some(8));
+        assert_eq!(depth("yuv420p10le"), Some(10));
+        assert_eq!(depth("yuva444p12be"), Some(12));
+        assert_eq!(depth("gbrp16le"), Some(16));
+        assert_eq!(depth("p010le"), Some(10));
+        assert_eq!(depth("p016le"), Some(16));
+        assert_eq!(depth("gray12le"), Some(12));
+        assert_eq!(depth("rgb48le"), Some(16));
+        assert_eq!(depth("rgba64be"), Some(16));
+    }
+
+    #[test]
+    fn tolerate_missing_values() {
+        let probe: Probe = serde_json::from_str(
+            r#"{"streams":[{"index":0,"codec_type":"video","nb_frames":"N/A"}],"format":{"duration":"N/A"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(probe.streams[0].nb_frames, None);
+        assert_eq!(probe.format.duration, None);
+        assert_eq!(probe.streams[0].bit_depth(), None);
+        assert_eq!(probe.streams[0].sar(), None);
+        assert_eq!(parse_ratio("0:1"), None);
+        assert_eq!(parse_ratio("N/A"), None);
+    }
+
+    #[test]
+    fn duration_from_tag() {
+        let probe: Probe = serde_json::from_str(
+            r#"{"streams":[
+                {"index":0,"codec_type":"video","tags":{"DURATION":"00:02:00.500000000"}},
+                {"index":1,"codec_type":"audio","tags":{"DURATION-eng":"01:00:00.000000000"}},
+                {"index":2,"codec_type":"audio","tags":{"DURATION":"N/A"}}
+            ]}"#,
+        )
+        .unwrap();
+
+        let durations: Vec<_> = probe.streams.iter().map(Stream::tag_duration).collect();
+        assert_eq!(durations, [Some(120.5), Some(3_600.0), None]);
+    }
+
+    #[test]
+    fn rotation_from_side_data_or_tag() {
+        let probe: Probe = serde_json::from_str(
+            r#"{"streams":[
+                {"index":0,"codec_type":"video","side_data_list":[{"side_data_type":"Display Matrix","displaymatrix":"...","rotation":-90}]},
+                {"index":1,"codec_type":"video","side_data_list":[{"side_data_type":"Display Matrix","rotation":90}],"tags":{"rotate":"0"}},
+                {"index":2,"codec_type":"video","tags":{"rotate":"180"}},
+                {"index":3,"codec_type":"video","side_data_list":[{"side_data_type":"Display Matrix","rotation":"-180.00"}]},
+                {"index":4,"codec_type":"video"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let rotations: Vec<_> = probe.streams.iter().map(Stream::rotation).collect();
+        assert_eq!(rotations, [90, 270, 180, 180, 0]);
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    cmp::{Ordering, max},
+    cmp::{Ordering, max, min},
     fmt,
     str::FromStr,
 };
@@ -11,8 +11,20 @@ pub enum Orientation {
     Portrait,  // 竖屏
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+/// 分辨率
+///
+/// 作为编码的分辨率限制时只看短边，横屏与竖屏的源视频都以短边为准，
+/// 例如 720p 把 1920x1080 缩小到 1280x720、把 1080x1920 缩小到 720x1280
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
 pub enum Resolution {
+    /// 8k
+    Fuhd,
+    /// 8k
+    Vfuhd,
+    /// DCI 4k
+    Dci4k,
+    /// DCI 4k
+    Vdci4k,
     /// 4k
     Uhd,
     /// 4k
@@ -22,6 +34,7 @@ pub enum Resolution {
     /// 2k
     Vqhd,
     /// 1080p
+    #[default]
     Fhd,
     /// 1080p
     Vfhd,
@@ -29,6 +42,14 @@ pub enum Resolution {
     Hd,
     /// 720p
     Vhd,
+    /// 480p
+    Fwvga,
+    /// 480p
+    Vfwvga,
+    /// 360p
+    Nhd,
+    /// 360p
+    Vnhd,
     Arbitrary {
         width: u16,
         height: u16,
@@ -49,10 +70,22 @@ pub enum ResolutionError {
     Parse(String),
 }
 
+/// 接受 `WxH` 与 360p、480p、720p、1080p、1440p、4k、8k 等别名
 impl FromStr for Resolution {
     type Err = ResolutionError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "360p" => return Ok(Self::Nhd),
+            "480p" => return Ok(Self::Fwvga),
+            "720p" => return Ok(Self::Hd),
+            "1080p" => return Ok(Self::Fhd),
+            "1440p" => return Ok(Self::Qhd),
+            "4k" | "2160p" => return Ok(Self::Uhd),
+            "8k" | "4320p" => return Ok(Self::Fuhd),
+            _ => {}
+        }
+
         match s.split_once('x') {
             Some(("", _)) => Err(ResolutionError::MissingWidth),
             Some((_, "")) => Err(ResolutionError::MissingHeight),
@@ -73,18 +106,16 @@ impl FromStr for Resolution {
     }
 }
 
-impl Default for Resolution {
-    fn default() -> Self {
-        Self::Fhd
-    }
-}
-
 impl Resolution {
     pub fn new(width: u16, height: u16) -> Result<Self, ResolutionError> {
         if width == 0 || height == 0 {
             return Err(ResolutionError::Zero);
         }
         match (width, height) {
+            (7_680, 4_320) => Ok(Self::Fuhd),
+            (4_320, 7_680) => Ok(Self::Vfuhd),
+            (4_096, 2_160) => Ok(Self::Dci4k),
+            (2_160, 4_096) => Ok(Self::Vdci4k),
             (3_840, 2_160) => Ok(Self::Uhd),
             (2_160, 3_840) => Ok(Self::Vuhd),
             (2_560, 1_440) => Ok(Self::Qhd),
@@ -93,22 +124,34 @@ impl Resolution {
             (1_080, 1_920) => Ok(Self::Vfhd),
             (1_280, 720) => Ok(Self::Hd),
             (720, 1_280) => Ok(Self::Vhd),
+            (854, 480) => Ok(Self::Fwvga),
+            (480, 854) => Ok(Self::Vfwvga),
+            (640, 360) => Ok(Self::Nhd),
+            (360, 640) => Ok(Self::Vnhd),
             _ => Ok(Self::Arbitrary { width, height }),
         }
     }
 
     pub fn pixels(&self) -> u32 {
         match self {
+            Resolution::Fuhd | Resolution::Vfuhd => 33_177_600,
+            Resolution::Dci4k | Resolution::Vdci4k => 8_847_360,
             Resolution::Uhd | Resolution::Vuhd => 8_294_400,
             Resolution::Qhd | Resolution::Vqhd => 3_686_400,
             Resolution::Fhd | Resolution::Vfhd => 2_073_600,
             Resolution::Hd | Resolution::Vhd => 921_600,
+            Resolution::Fwvga | Resolution::Vfwvga => 409_920,
+            Resolution::Nhd | Resolution::Vnhd => 230_400,
             &Resolution::Arbitrary { width, height } => (width as u32) * (height as u32),
         }
     }
 
     pub fn width(&self) -> u16 {
         match self {
+            Resolution::Fuhd => 7_680,
+            Resolution::Vfuhd => 4_320,
+            Resolution::Dci4k => 4_096,
+            Resolution::Vdci4k => 2_160,
             Resolution::Uhd => 3_840,
             Resolution::Vuhd => 2_160,
             Resolution::Qhd => 2_560,
@@ -117,12 +160,20 @@ impl Resolution {
             Resolution::Vfhd => 1_080,
             Resolution::Hd => 1_280,
             Resolution::Vhd => 720,
+            Resolution::Fwvga => 854,
+            Resolution::Vfwvga => 480,
+            Resolution::Nhd => 640,
+            Resolution::Vnhd => 360,
             &Resolution::Arbitrary { width, height: _ } => width,
         }
     }
 
     pub fn height(&self) -> u16 {
         match self {
+            Resolution::Fuhd => 4_320,
+            Resolution::Vfuhd => 7_680,
+            Resolution::Dci4k => 2_160,
+            Resolution::Vdci4k => 4_096,
             Resolution::Uhd => 2_160,
             Resolution::Vuhd => 3_840,
             Resolution::Qhd => 1_440,
@@ -131,6 +182,10 @@ impl Resolution {
             Resolution::Vfhd => 1_920,
             Resolution::Hd => 720,
             Resolution::Vhd => 1_280,
+            Resolution::Fwvga => 480,
+            Resolution::Vfwvga => 854,
+            Resolution::Nhd => 360,
+            Resolution::Vnhd => 640,
             &Resolution::Arbitrary { width: _, height } => height,
         }
     }
@@ -147,11 +202,20 @@ impl Resolution {
     pub fn get_primary_dimension(&self) -> u16 {
         max(self.width(), self.height())
     }
+
+    /// 短边，作为分辨率限制时使用
+    pub fn short_side(&self) -> u16 {
+        min(self.width(), self.height())
+    }
 }
 
 impl fmt::Display for Resolution {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Resolution::Fuhd => write!(f, "7680x4320"),
+            Resolution::Vfuhd => write!(f, "4320x7680"),
+            Resolution::Dci4k => write!(f, "4096x2160"),
+            Resolution::Vdci4k => write!(f, "2160x4096"),
             Resolution::Uhd => write!(f, "3840x2160"),
             Resolution::Vuhd => write!(f, "2160x3840"),
             Resolution::Qhd => write!(f, "2560x1440"),
@@ -160,7 +224,34 @@ impl fmt::Display for Resolution {
             Resolution::Vfhd => write!(f, "1080x1920"),
             Resolution::Hd => write!(f, "1280x720"),
             Resolution::Vhd => write!(f, "720x1280"),
+            Resolution::Fwvga => write!(f, "854x480"),
+            Resolution::Vfwvga => write!(f, "480x854"),
+            Resolution::Nhd => write!(f, "640x360"),
+            Resolution::Vnhd => write!(f, "360x640"),
             Resolution::Arbitrary { width, height } => write!(f, "{}x{}", width, height),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_aliases() {
+        assert_eq!("720p".parse(), Ok(Resolution::Hd));
+        assert_eq!("4K".parse(), Ok(Resolution::Uhd));
+        assert_eq!("8k".parse(), Ok(Resolution::Fuhd));
+        assert_eq!("360p".parse(), Ok(Resolution::Nhd));
+        assert_eq!("4096x2160".parse(), Ok(Resolution::Dci4k));
+        assert_eq!("480x854".parse(), Ok(Resolution::Vfwvga));
+        assert_eq!(
+            "999p".parse::<Resolution>(),
+            Err(ResolutionError::NoDelimiterX)
+        );
+
+        assert_eq!(Resolution::Vfwvga.short_side(), 480);
+        assert_eq!(Resolution::Fwvga.to_string(), "854x480");
+        assert_eq!(Resolution::Vdci4k.pixels(), 4_096 * 2_160);
+    }
+}
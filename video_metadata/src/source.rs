@@ -0,0 +1,63 @@
+use serde::Serialize;
+use std::fmt;
+
+/// 某项信息的来源，按优先级从高到低排列
+///
+/// 裸 ts、部分 webm 或录制中断的文件缺少封装格式中的时长等信息，需要逐级回退
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// 封装格式信息
+    Format,
+    /// 视频流信息，帧率对应 avg_frame_rate
+    Stream,
+    /// 视频流的基础帧率 r_frame_rate
+    RFrameRate,
+    /// 流的 DURATION 标签，mkv/webm 常见
+    Tag,
+    /// 统计视频流的数据包数量，按帧率推算时长
+    PacketCount,
+    /// 文件系统
+    Filesystem,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Source::Format => "format",
+            Source::Stream => "stream",
+            Source::RFrameRate => "r_frame_rate",
+            Source::Tag => "tag",
+            Source::PacketCount => "packet count",
+            Source::Filesystem => "filesystem",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// 时长、帧率与文件大小各自的来源，时长无法获取时为 `None`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Sources {
+    pub duration: Option<Source>,
+    pub fps: Source,
+    pub size: Source,
+}
+
+impl Sources {
+    /// 是否有信息不是取自首选来源
+    pub fn is_fallback(&self) -> bool {
+        self.duration != Some(Source::Format)
+            || self.fps != Source::Stream
+            || self.size != Source::Format
+    }
+}
+
+impl fmt::Display for Sources {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.duration {
+            Some(source) => write!(f, "duration from {}", source)?,
+            None => write!(f, "unknown duration")?,
+        }
+        write!(f, ", fps from {}, size from {}", self.fps, self.size)
+    }
+}
@@ -1,3 +1,4 @@
+use ffmpeg_command_builder::FfmpegError;
 use ffmpeg_progress_monitor::ProgressMonitorError;
 use std::io;
 
@@ -8,11 +9,15 @@ pub enum ThumbnailError {
     #[error(transparent)]
     IO(#[from] io::Error),
     #[error(transparent)]
+    Ffmpeg(#[from] FfmpegError),
+    #[error(transparent)]
     ProgressMonitor(#[from] ProgressMonitorError),
-    #[error("failed to get stderr")]
+    #[error("video duration is unknown, can't pick frames for the thumbnail")]
+    UnknownDuration,
+    #[error("failed to get stdout or stderr of ffmpeg")]
     TakeStd,
-    #[error("FFmpeg exited with status {0}")]
-    FfmpegExit(String),
+    #[error("FFmpeg failed ({status}): {log}")]
+    FfmpegExit { status: String, log: String },
     #[error("delimiter x not found")]
     NoDelimiterX,
     #[error("row is missing")]
@@ -1,8 +1,10 @@
 use crate::{Grid, ThumbnailError, error::ThumbnailResult};
-use ffmpeg_command_builder::FfmpegCommandBuilder;
+use ffmpeg_command_builder::{Capabilities, ErrorLog, FfmpegCommandBuilder, Filter, FilterChain};
+use ffmpeg_progress_monitor::{ProgressMonitor, ProgressSnapshot};
 use std::{
     path::Path,
     process::{Command, Stdio},
+    time::Duration,
 };
 
 pub struct Generator<'a> {
@@ -14,7 +16,24 @@ pub struct Generator<'a> {
     ratio: f32,
 }
 
+/// 缩略图生成依赖的滤镜
+const REQUIRED_FILTERS: [&str; 5] = ["select", "fps", "scale", "setsar", "tile"];
+
 impl<'a> Generator<'a> {
+    /// 在扫描视频之前确认 ffmpeg 包含所需的滤镜、输出扩展名对应的封装格式与 null 输出
+    pub fn check_capabilities(
+        capabilities: &Capabilities,
+        output_extension: &str,
+    ) -> ThumbnailResult<()> {
+        for filter in REQUIRED_FILTERS {
+            capabilities.require_filter(filter)?;
+        }
+        capabilities.require_muxer_for_extension(output_extension)?;
+        capabilities.require_muxer("null")?;
+        Ok(())
+    }
+
+    /// ffmpeg 的能力由 [`Generator::check_capabilities`] 提前检查
     pub fn new(
         input: &'a Path,
         output: &'a Path,
@@ -22,18 +41,30 @@ impl<'a> Generator<'a> {
         grid: Grid,
         base_dimesion: u16,
         ratio: f32,
-    ) -> Self {
-        Self {
+    ) -> ThumbnailResult<Self> {
+        // 没有时长时无法均匀选取关键帧
+        if !duration.is_finite() || duration <= 0.0 {
+            return Err(ThumbnailError::UnknownDuration);
+        }
+
+        Ok(Self {
             input,
             output,
             duration,
             grid,
             base_dimesion,
             ratio,
-        }
+        })
     }
 
-    pub(crate) fn build_ffmpeg_command(&self) -> ThumbnailResult<Command> {
+    /// 构建生成缩略图所需要的 `Command`
+    ///
+    /// tile 滤镜在所有格子填满后才输出一帧，缩略图输出的 out_time 无法反映扫描进度，
+    /// 因此额外添加一个丢弃关键帧的 null 输出，进度中的 out_time 取所有输出中最大的时间戳
+    ///
+    /// # ffmpeg命令举例
+    /// ffmpeg -hide_banner -v error -skip_frame nokey -y -progress pipe:1 -i input.mp4 -map 0:v -vf select=eq(pict_type\,I),fps=0.041666668,scale=355:200,setsar=1,tile=2x2 -fps_mode vfr -frames:v 1 -update 1 -q:v 2 output.jpg -map 0:v -f null -
+    pub fn build_ffmpeg_command(&self) -> ThumbnailResult<Command> {
         let (width, height) = self.calc_dimension();
         let (row, col) = match self.grid {
             Grid { row: 0, col: 0 } => self.get_default_grid_config(),
@@ -42,40 +73,57 @@ impl<'a> Generator<'a> {
 
         Ok(FfmpegCommandBuilder::new()
             .global_opt("-hide_banner -v error -skip_frame nokey -y")
+            .global_pair("-progress", "pipe:1")
             .input(self.input.to_string_lossy())
-            .output_opt("-map 0:v")
-            .output_opt(format!(
-                "-vf select='eq(pict_type,I)',fps=1/{},scale={}:{},tile={}x{}",
-                self.interval(),
-                width,
-                height,
-                row,
-                col
-            ))
+            .output_pair("-map", "0:v")
+            .video_filter(
+                FilterChain::new()
+                    .filter(Filter::new("select").arg("eq(pict_type,I)"))
+                    .filter(Filter::new("fps").arg(self.rate()))
+                    .filter(Filter::new("scale").arg(width).arg(height))
+                    // 宽高已按显示宽高比计算，输出方形像素避免图片查看器再次拉伸
+                    .filter(Filter::new("setsar").arg(1))
+                    .filter(Filter::new("tile").arg(format!("{}x{}", row, col))),
+            )
             .output_opt("-fps_mode vfr -frames:v 1 -update 1 -q:v 2")
             .output(self.output.to_string_lossy())
-            .build())
+            .output_pair("-map", "0:v")
+            .output_pair("-f", "null")
+            .output("-")
+            .build()?)
     }
 
-    pub fn generate(&self) -> ThumbnailResult<()> {
-        let mut child = self
-            .build_ffmpeg_command()?
+    pub fn generate(
+        &self,
+        monitor: ProgressMonitor,
+    ) -> ThumbnailResult<(Duration, ProgressSnapshot)> {
+        let mut command = self.build_ffmpeg_command()?;
+
+        // 进度走 stdout，stderr 只剩错误信息
+        let mut child = command
+            .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()?;
 
-        // let mut output = String::new();
-        // let _ = child.stderr.take().unwrap().read_to_string(&mut output);
-        // println!("{}", output);
+        let error_log = ErrorLog::spawn(child.stderr.take().ok_or(ThumbnailError::TakeStd)?);
 
+        let result = monitor.watch(&mut child);
         let status = child.wait()?;
-        if !status.success() {
-            return Err(ThumbnailError::FfmpegExit(format!("{}", status)));
-        }
 
-        Ok(())
+        match result {
+            // 卡住或超时时 ffmpeg 已被结束，直接报告原因
+            Err(e) if e.is_timeout() => Err(e.into()),
+            // ffmpeg 异常退出时进度也无法正常结束，优先报告 ffmpeg 的错误信息
+            _ if !status.success() => Err(ThumbnailError::FfmpegExit {
+                status: status.to_string(),
+                log: error_log.finish(),
+            }),
+            result => Ok(result?),
+        }
     }
 
-    fn interval(&self) -> u32 {
+    /// 每秒选取的帧数，格子之间的间隔至少 1 秒，时长很短的视频允许出现重复的画面
+    fn rate(&self) -> f32 {
         let grid_count = match self.grid {
             Grid { row: 0, col: 0 } => {
                 let (r, c) = self.get_default_grid_config();
@@ -84,7 +132,8 @@ impl<'a> Generator<'a> {
             _ => self.grid.count(),
         };
 
-        (self.duration / ((grid_count + 1) as f32)) as u32
+        let interval = (self.duration / (grid_count + 1) as f32).max(1.0);
+        interval.recip()
     }
 
     fn calc_dimension(&self) -> (u16, u16) {
@@ -113,3 +162,70 @@ impl<'a> Generator<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ffmpeg_command_builder::FfmpegError;
+    use utils::get_command_args;
+
+    #[test]
+    fn report_progress_through_null_output() -> ThumbnailResult<()> {
+        let generator = Generator::new(
+            Path::new("input.mp4"),
+            Path::new("output.jpg"),
+            120.0,
+            Grid::default(),
+            200,
+            16.0 / 9.0,
+        )?;
+
+        let command = generator.build_ffmpeg_command()?;
+        let args = get_command_args(&command).to_string_lossy().to_string();
+        assert!(args.contains("-progress pipe:1 -i input.mp4"), "{}", args);
+        assert!(args.ends_with("output.jpg -map 0:v -f null -"), "{}", args);
+
+        let capabilities =
+            Capabilities::new(Vec::<String>::new(), REQUIRED_FILTERS, ["image2", "null"]);
+        assert!(Generator::check_capabilities(&capabilities, "jpg").is_ok());
+
+        let capabilities = Capabilities::new(Vec::<String>::new(), REQUIRED_FILTERS, ["image2"]);
+        let result = Generator::check_capabilities(&capabilities, "jpg");
+        assert!(matches!(
+            result,
+            Err(ThumbnailError::Ffmpeg(FfmpegError::MissingMuxer(_)))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_unknown_duration() -> ThumbnailResult<()> {
+        let generator = |duration| {
+            Generator::new(
+                Path::new("input.mp4"),
+                Path::new("output.jpg"),
+                duration,
+                Grid::default(),
+                200,
+                16.0 / 9.0,
+            )
+        };
+
+        assert!(matches!(
+            generator(0.0),
+            Err(ThumbnailError::UnknownDuration)
+        ));
+
+        // 短于格子数 + 1 秒的视频，间隔取 1 秒
+        let command = generator(3.0)?.build_ffmpeg_command()?;
+        let args = get_command_args(&command).to_string_lossy().to_string();
+        assert!(args.contains("fps=1,"), "{}", args);
+
+        let command = generator(120.0)?.build_ffmpeg_command()?;
+        let args = get_command_args(&command).to_string_lossy().to_string();
+        assert!(args.contains("fps=0.041666668,"), "{}", args);
+
+        Ok(())
+    }
+}